@@ -0,0 +1,287 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use rand::seq::SliceRandom;
+
+use crate::board::{Board, CellState, Player};
+use crate::gomoku::{GomokuPosition, ThreatBoard};
+use crate::mcts::{self, Budget, Position};
+use crate::search::{self, Limits};
+use crate::transposition::TranspositionTable;
+use crate::ultimate::{MetaBoard, UltimatePosition};
+
+/// Longest a Monte Carlo search may think on a Gomoku board, whose playouts
+/// run for a hundred moves or more where the playout budget was sized for
+/// boards of a few dozen cells.
+pub const GOMOKU_TIME_LIMIT: Duration = Duration::from_millis(1_500);
+
+/// Thinking time of Hard on an Ultimate board, worth about four times the
+/// default playouts of Monte Carlo.
+pub const ULTIMATE_HARD_TIME: Duration = Duration::from_secs(3);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Level {
+    #[default]
+    Easy,
+    Medium,
+    Hard,
+    MonteCarlo,
+}
+
+impl Level {
+    pub const ALL: [Level; 4] = [Level::Easy, Level::Medium, Level::Hard, Level::MonteCarlo];
+
+    /// Level whose `Debug` name is `name`.
+    pub fn from_name(name: &str) -> Option<Level> {
+        Level::ALL.into_iter().find(|level| format!("{level:?}") == name)
+    }
+
+    /// Position in `Level::ALL`.
+    pub fn index(self) -> usize {
+        match self {
+            Level::Easy => 0,
+            Level::Medium => 1,
+            Level::Hard => 2,
+            Level::MonteCarlo => 3,
+        }
+    }
+}
+
+/// Settings and state of the computer player, kept for the whole game.
+/// Clones share the transposition table and the stop flag, so a clone can
+/// search on another thread and still be cancelled through the flag handed
+/// out by `new_stop_flag`.
+#[derive(Debug, Clone, Default)]
+pub struct Engine {
+    pub limits: Limits,
+    pub mcts: mcts::Config,
+    table: Arc<Mutex<TranspositionTable>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Engine {
+    /// Picks a move for `player`. Returns `None` when there is nothing left
+    /// to play or the search was cancelled before finding a move.
+    pub fn choose_move(&self, level: Level, board: &Board, player: Player) -> Option<usize> {
+        if board.available_moves().is_empty() {
+            return None;
+        }
+
+        match level {
+            Level::Easy => Some(free_spot(board)),
+            Level::Medium => Some(closest_spot(board, player)),
+            Level::Hard => {
+                let mut table = self.table.lock().unwrap_or_else(PoisonError::into_inner);
+                search::search_with_table(board, player, self.limits, &mut table, &self.stop)
+                    .map(|result| result.index)
+            }
+            Level::MonteCarlo => mcts::search_until(board, player, &self.mcts, &self.stop),
+        }
+    }
+
+    /// Picks a move for `player` in an Ultimate game. Only the tree search
+    /// knows the Ultimate rules, so both search levels use it: Hard for
+    /// `ULTIMATE_HARD_TIME`, Monte Carlo with the configured budget.
+    pub fn choose_ultimate_move(&self, level: Level, board: &Board, meta: &MetaBoard, player: Player) -> Option<usize> {
+        let position = UltimatePosition {
+            board: board.clone(),
+            meta: meta.clone(),
+        };
+        let moves = position.available_moves();
+        if moves.is_empty() {
+            return None;
+        }
+
+        match level {
+            Level::Easy => Some(random_spot(&moves)),
+            Level::Medium => Some(ultimate_spot(&position, &moves, player)),
+            Level::Hard => {
+                let config = mcts::Config {
+                    budget: Budget::Time(ULTIMATE_HARD_TIME),
+                    ..self.mcts
+                };
+                mcts::search_until(&position, player, &config, &self.stop)
+            }
+            Level::MonteCarlo => mcts::search_until(&position, player, &self.mcts, &self.stop),
+        }
+    }
+
+    /// Picks a move for `player` on a Gomoku board, among the cells near the
+    /// marks already played. Medium looks one move ahead at the threats of
+    /// `ThreatBoard`, and Hard searches them as deep as its limits allow.
+    /// Monte Carlo runs for `GOMOKU_TIME_LIMIT` at most, whatever its budget.
+    pub fn choose_gomoku_move(&self, level: Level, board: &Board, player: Player) -> Option<usize> {
+        let position = GomokuPosition::new(board);
+        let moves = position.available_moves();
+        if moves.is_empty() {
+            return None;
+        }
+
+        let search = |limits| {
+            let mut table = self.table.lock().unwrap_or_else(PoisonError::into_inner);
+            search::search_with_table(&ThreatBoard::new(board), player, limits, &mut table, &self.stop)
+                .map(|result| result.index)
+        };
+        match level {
+            Level::Easy => Some(random_spot(&moves)),
+            Level::Medium => search(Limits {
+                depth: Some(1),
+                nodes: None,
+            }),
+            Level::Hard => search(self.limits),
+            Level::MonteCarlo => {
+                let budget = match self.mcts.budget {
+                    Budget::Time(limit) => limit.min(GOMOKU_TIME_LIMIT),
+                    Budget::Iterations(_) => GOMOKU_TIME_LIMIT,
+                };
+                let config = mcts::Config {
+                    budget: Budget::Time(budget),
+                    ..self.mcts
+                };
+                mcts::search_until(&position, player, &config, &self.stop)
+            }
+        }
+    }
+
+    /// Gives this engine a stop flag of its own and returns it. Raising the
+    /// flag stops the searches of this engine and of its clones made from
+    /// now on, leaving earlier clones alone, so that each search can be
+    /// cancelled without stopping the next one.
+    pub fn new_stop_flag(&mut self) -> Arc<AtomicBool> {
+        self.stop = Arc::new(AtomicBool::new(false));
+        Arc::clone(&self.stop)
+    }
+}
+
+pub fn free_spot(board: &Board) -> usize {
+    random_spot(&board.available_moves())
+}
+
+fn random_spot(spots: &[usize]) -> usize {
+    *spots
+        .choose(&mut rand::thread_rng())
+        .expect("no spot left to choose from")
+}
+
+/// Free cell completing a line the opponent of `player` is one mark away
+/// from winning.
+pub fn play_block(board: &Board, player: Player) -> Option<usize> {
+    let count = |line: &[usize], state: CellState| line.iter().filter(|&&pos| board.cells()[pos] == state).count();
+
+    board
+        .lines()
+        .iter()
+        .find(|line| {
+            count(line, CellState::Occupied(player.opponent())) == board.win_length() - 1
+                && count(line, CellState::Empty) == 1
+        })
+        .and_then(|line| line.iter().find(|&&pos| board.cells()[pos] == CellState::Empty).copied())
+}
+
+pub fn closest_spot(board: &Board, player: Player) -> usize {
+    // Completing our own line is blocking it for the opponent's opponent.
+    if let Some(win) = play_block(board, player.opponent()) {
+        return win;
+    }
+    if let Some(block) = play_block(board, player) {
+        return block;
+    }
+
+    if (board.width(), board.height(), board.layers(), board.win_length()) == (3, 3, 1, 3) {
+        classic_spot(board, player)
+    } else {
+        central_spot(board)
+    }
+}
+
+/// Free cell nearest to the middle of the board, ties broken at random.
+fn central_spot(board: &Board) -> usize {
+    let distance = |position: usize| {
+        let (column, row) = board.coordinates(position);
+        let dx = 2 * column as isize - (board.width() as isize - 1);
+        let dy = 2 * row as isize - (board.height() as isize - 1);
+        let dz = 2 * board.layer(position) as isize - (board.layers() as isize - 1);
+        dx * dx + dy * dy + dz * dz
+    };
+
+    let available_spots = board.available_moves();
+    let closest = available_spots.iter().map(|&pos| distance(pos)).min().expect("no spot left to choose from");
+    let candidates: Vec<usize> = available_spots.into_iter().filter(|&pos| distance(pos) == closest).collect();
+    random_spot(&candidates)
+}
+
+/// Whether `player` playing `index` wins the game or claims a local board.
+fn claims(position: &UltimatePosition, index: usize, player: Player) -> bool {
+    let mut next = position.clone();
+    let won = next.play(index, player);
+    won || next.meta.claimed().played_count() > position.meta.claimed().played_count()
+}
+
+/// Ultimate counterpart of `closest_spot`: claim a local board, or keep the
+/// opponent from claiming the one being played, and otherwise avoid sending
+/// them to a local board they can claim.
+fn ultimate_spot(position: &UltimatePosition, moves: &[usize], player: Player) -> usize {
+    let wins = |index: usize| position.clone().play(index, player);
+    if let Some(&win) = moves.iter().find(|&&index| wins(index)) {
+        return win;
+    }
+    if let Some(&claim) = moves.iter().find(|&&index| claims(position, index, player)) {
+        return claim;
+    }
+    if let Some(&block) = moves.iter().find(|&&index| claims(position, index, player.opponent())) {
+        return block;
+    }
+
+    let safe: Vec<usize> = moves
+        .iter()
+        .copied()
+        .filter(|&index| {
+            let mut next = position.clone();
+            next.play(index, player);
+            !next.available_moves().into_iter().any(|reply| claims(&next, reply, player.opponent()))
+        })
+        .collect();
+    random_spot(if safe.is_empty() { moves } else { &safe })
+}
+
+/// Opening heuristics for the 3x3 board, for either side.
+fn classic_spot(board: &Board, player: Player) -> usize {
+    let available_spots = board.available_moves();
+    let hu_played_spots = board.played_moves(player.opponent());
+    let playing_count = board.played_count();
+
+    // Opening the game: the center, then the corner facing the opponent's
+    // corner, or any free corner after an edge reply.
+    if playing_count == 0 {
+        return 4;
+    }
+    if playing_count == 2 && board.played_moves(player) == [4] {
+        let corners: Vec<usize> = [0, 2, 6, 8].into_iter().filter(|&pos| board.is_free(pos)).collect();
+        return match hu_played_spots[..] {
+            [corner @ (0 | 2 | 6 | 8)] => 8 - corner,
+            _ => random_spot(&corners),
+        };
+    }
+
+    if hu_played_spots.contains(&4) && playing_count == 1 {
+        return random_spot(&[0, 2, 6, 8]);
+    }
+    if hu_played_spots.contains(&4) && playing_count == 3 {
+        let reply = [(2, 6), (6, 2), (0, 8), (8, 0)]
+            .into_iter()
+            .find(|&(corner, opposite)| hu_played_spots.contains(&corner) && board.is_free(opposite));
+        if let Some((_, opposite)) = reply {
+            return opposite;
+        }
+    }
+    if !hu_played_spots.contains(&4) && playing_count == 1 {
+        return 4;
+    }
+    if !hu_played_spots.contains(&4) && playing_count == 3 {
+        if let Some(edge) = [1, 7, 3, 5].into_iter().find(|&edge| board.is_free(edge)) {
+            return edge;
+        }
+    }
+    random_spot(&available_spots)
+}
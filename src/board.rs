@@ -0,0 +1,405 @@
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Player {
+    X,
+    O,
+}
+
+impl Player {
+    pub const ALL: [Player; 2] = [Player::X, Player::O];
+
+    pub fn opponent(self) -> Player {
+        match self {
+            Player::X => Player::O,
+            Player::O => Player::X,
+        }
+    }
+
+    pub fn index(self) -> usize {
+        match self {
+            Player::X => 0,
+            Player::O => 1,
+        }
+    }
+}
+
+impl std::fmt::Display for Player {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Player::X => "X",
+            Player::O => "O",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CellState {
+    #[default]
+    Empty,
+    Occupied(Player),
+}
+
+/// Steps along a row, a column and both diagonals of a layer, then every
+/// direction leading to the next layer.
+const DIRECTIONS: [(isize, isize, isize); 13] = [
+    (1, 0, 0),
+    (0, 1, 0),
+    (1, 1, 0),
+    (1, -1, 0),
+    (0, 0, 1),
+    (1, 0, 1),
+    (-1, 0, 1),
+    (0, 1, 1),
+    (0, -1, 1),
+    (1, 1, 1),
+    (1, -1, 1),
+    (-1, 1, 1),
+    (-1, -1, 1),
+];
+
+/// Cells are stored layer by layer, each layer row by row. Flat boards have
+/// a single layer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Board {
+    width: usize,
+    height: usize,
+    layers: usize,
+    win_length: usize,
+    /// Whether only runs of exactly `win_length` marks win.
+    exact: bool,
+    cells: Vec<CellState>,
+    lines: Arc<[Vec<usize>]>,
+    /// Cells extending each line by one at either end, if on the board.
+    line_ends: Arc<[[Option<usize>; 2]]>,
+    cell_lines: Arc<[Vec<usize>]>,
+    symmetries: Arc<[Vec<usize>]>,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::new(3, 3, 3)
+    }
+}
+
+impl Board {
+    pub fn new(width: usize, height: usize, win_length: usize) -> Self {
+        Board::with_layers(width, height, 1, win_length)
+    }
+
+    /// A board of `layers` stacked `width` x `height` layers, where lines
+    /// also run across the layers, as in 4x4x4 Qubic.
+    pub fn with_layers(width: usize, height: usize, layers: usize, win_length: usize) -> Self {
+        assert!(width > 0 && height > 0 && layers > 0, "board dimensions must be positive");
+        assert!(
+            win_length > 0 && win_length <= width.max(height).max(layers),
+            "win length {win_length} does not fit on a {width}x{height}x{layers} board"
+        );
+
+        let (lines, line_ends): (Vec<_>, Vec<_>) =
+            Self::compute_lines(width, height, layers, win_length).into_iter().unzip();
+        let mut cell_lines = vec![Vec::new(); width * height * layers];
+        for (index, line) in lines.iter().enumerate() {
+            for &pos in line {
+                cell_lines[pos].push(index);
+            }
+        }
+
+        Board {
+            width,
+            height,
+            layers,
+            win_length,
+            exact: false,
+            cells: vec![CellState::Empty; width * height * layers],
+            lines: lines.into(),
+            line_ends: line_ends.into(),
+            cell_lines: cell_lines.into(),
+            symmetries: Self::compute_symmetries(width, height, layers).into(),
+        }
+    }
+
+    /// Every run of `win_length` cells along a row, a column or a diagonal,
+    /// within a layer or across them, with the cells just before and after
+    /// it. A 4x4x4 board has 76 of them.
+    fn compute_lines(
+        width: usize,
+        height: usize,
+        layers: usize,
+        win_length: usize,
+    ) -> Vec<(Vec<usize>, [Option<usize>; 2])> {
+        let mut lines = Vec::new();
+        let span = win_length as isize - 1;
+        let fits = |value: isize, size: usize| (0..size as isize).contains(&value);
+        // A single cell is the same line whichever way it runs.
+        let directions = if span == 0 { &DIRECTIONS[..1] } else { &DIRECTIONS[..] };
+
+        for z in 0..layers as isize {
+            for y in 0..height as isize {
+                for x in 0..width as isize {
+                    for &(dx, dy, dz) in directions {
+                        if !fits(x + dx * span, width) || !fits(y + dy * span, height) || !fits(z + dz * span, layers) {
+                            continue;
+                        }
+
+                        let cell = |step: isize| {
+                            let (x, y, z) = (x + dx * step, y + dy * step, z + dz * step);
+                            (fits(x, width) && fits(y, height) && fits(z, layers))
+                                .then(|| (z as usize * height + y as usize) * width + x as usize)
+                        };
+                        lines.push(((0..=span).filter_map(cell).collect(), [cell(-1), cell(span + 1)]));
+                    }
+                }
+            }
+        }
+        lines
+    }
+
+    /// Cell permutations for the rotations and reflections mapping the board
+    /// onto itself: every combination of flipped axes, with the axes of equal
+    /// length in any order. That is eight symmetries for a square board, four
+    /// for a rectangle and 48 for a cube. The identity always comes first.
+    fn compute_symmetries(width: usize, height: usize, layers: usize) -> Vec<Vec<usize>> {
+        let sizes = [width, height, layers];
+        let orders = [[0, 1, 2], [1, 0, 2], [0, 2, 1], [2, 1, 0], [1, 2, 0], [2, 0, 1]]
+            .into_iter()
+            .filter(|order| order.iter().enumerate().all(|(axis, &from)| sizes[from] == sizes[axis]));
+        // Flipping an axis of length 1 changes nothing.
+        let flips: Vec<usize> = (0..8)
+            .filter(|flips| (0..3).all(|axis| flips & (1 << axis) == 0 || sizes[axis] > 1))
+            .collect();
+
+        orders
+            .flat_map(|order| flips.iter().map(move |&flips| (order, flips)))
+            .map(|(order, flips)| {
+                (0..width * height * layers)
+                    .map(|position| {
+                        let coordinates = [position % width, position / width % height, position / (width * height)];
+                        let mut moved = order.map(|axis| coordinates[axis]);
+                        for (axis, coordinate) in moved.iter_mut().enumerate() {
+                            if flips & (1 << axis) != 0 {
+                                *coordinate = sizes[axis] - 1 - *coordinate;
+                            }
+                        }
+                        (moved[2] * height + moved[1]) * width + moved[0]
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The same board where a run longer than `win_length` does not win, as
+    /// in standard Gomoku where only exactly five in a row counts.
+    pub fn exact(self) -> Self {
+        Board { exact: true, ..self }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.exact
+    }
+
+    /// An empty board with the same dimensions and win length.
+    pub fn cleared(&self) -> Self {
+        Board {
+            cells: vec![CellState::Empty; self.cells.len()],
+            ..self.clone()
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn layers(&self) -> usize {
+        self.layers
+    }
+
+    pub fn win_length(&self) -> usize {
+        self.win_length
+    }
+
+    /// Number of cells, taken or not.
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Cell at `column` and `row` of the first layer.
+    pub fn index(&self, column: usize, row: usize) -> Option<usize> {
+        self.index_in_layer(column, row, 0)
+    }
+
+    pub fn index_in_layer(&self, column: usize, row: usize, layer: usize) -> Option<usize> {
+        (column < self.width && row < self.height && layer < self.layers)
+            .then_some((layer * self.height + row) * self.width + column)
+    }
+
+    /// Column and row of a cell within its layer.
+    pub fn coordinates(&self, position: usize) -> (usize, usize) {
+        (position % self.width, position / self.width % self.height)
+    }
+
+    pub fn layer(&self, position: usize) -> usize {
+        position / (self.width * self.height)
+    }
+
+    /// Name of a cell as used in move lists: its column as a letter and its
+    /// row as a number counted from the top, e.g. "b2" for the middle of a
+    /// 3x3 board. On boards with several layers the layer comes first as a
+    /// capital letter, e.g. "Ba1".
+    pub fn cell_name(&self, position: usize) -> String {
+        let (column, row) = self.coordinates(position);
+        let layer = match self.layers {
+            1 => String::new(),
+            _ => ((b'A' + self.layer(position) as u8) as char).to_string(),
+        };
+        format!("{layer}{}{}", (b'a' + column as u8) as char, row + 1)
+    }
+
+    /// Inverse of `cell_name`.
+    pub fn parse_cell(&self, name: &str) -> Option<usize> {
+        let mut chars = name.chars();
+        let layer = match self.layers {
+            1 => 0,
+            _ => chars.next().filter(char::is_ascii_uppercase)? as usize - 'A' as usize,
+        };
+        let column = chars.next().filter(char::is_ascii_lowercase)? as usize - 'a' as usize;
+        let row: usize = chars.as_str().parse().ok()?;
+        self.index_in_layer(column, row.checked_sub(1)?, layer)
+    }
+
+    pub fn cells(&self) -> &[CellState] {
+        &self.cells
+    }
+
+    pub fn cell(&self, position: usize) -> Option<CellState> {
+        self.cells.get(position).copied()
+    }
+
+    pub fn is_free(&self, position: usize) -> bool {
+        self.cell(position) == Some(CellState::Empty)
+    }
+
+    pub fn make_move(&mut self, position: usize, player: Player) {
+        self.cells[position] = CellState::Occupied(player);
+    }
+
+    pub fn clear(&mut self, position: usize) {
+        self.cells[position] = CellState::Empty;
+    }
+
+    pub fn available_moves(&self) -> Vec<usize> {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &cell)| if cell == CellState::Empty { Some(i) } else { None })
+            .collect()
+    }
+
+    pub fn played_moves(&self, player: Player) -> Vec<usize> {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &cell)| if cell == CellState::Occupied(player) { Some(i) } else { None })
+            .collect()
+    }
+
+    pub fn played_count(&self) -> usize {
+        self.cells.iter().filter(|&&cell| cell != CellState::Empty).count()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.cells.iter().all(|&cell| cell != CellState::Empty)
+    }
+
+    pub fn lines(&self) -> &[Vec<usize>] {
+        &self.lines
+    }
+
+    /// Each symmetry maps a cell index to the index it is moved to.
+    pub fn symmetries(&self) -> &[Vec<usize>] {
+        &self.symmetries
+    }
+
+    pub fn lines_through(&self, position: usize) -> impl Iterator<Item = &[usize]> + '_ {
+        self.cell_lines[position].iter().map(|&index| self.lines[index].as_slice())
+    }
+
+    /// Indices in `lines` of the lines going through `position`.
+    pub fn line_indices(&self, position: usize) -> &[usize] {
+        &self.cell_lines[position]
+    }
+
+    /// Whether `player` holds every cell of line `index`, and on an exact
+    /// board neither cell extending it.
+    fn holds(&self, index: usize, player: Player) -> bool {
+        let mark = CellState::Occupied(player);
+        self.lines[index].iter().all(|&pos| self.cells[pos] == mark)
+            && !(self.exact && self.line_ends[index].iter().flatten().any(|&pos| self.cells[pos] == mark))
+    }
+
+    /// Whether `player` holds a complete line going through `position`,
+    /// which is all that needs checking right after they played there.
+    pub fn is_winning_move(&self, position: usize, player: Player) -> bool {
+        self.cell_lines[position].iter().any(|&index| self.holds(index, player))
+    }
+
+    pub fn check_win(&self, player: Player) -> bool {
+        self.winning_lines(player).next().is_some()
+    }
+
+    /// Every line entirely held by `player`.
+    pub fn winning_lines(&self, player: Player) -> impl Iterator<Item = &[usize]> + '_ {
+        (0..self.lines.len())
+            .filter(move |&index| self.holds(index, player))
+            .map(|index| self.lines[index].as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_cell_lines_are_not_repeated() {
+        let board = Board::new(3, 3, 1);
+        assert_eq!(board.lines().len(), 9);
+        assert!(board.lines_through(4).eq([&[4][..]]));
+    }
+
+    #[test]
+    fn square_board_lines_and_symmetries() {
+        let board = Board::new(3, 3, 3);
+        assert_eq!(board.lines().len(), 8);
+        assert_eq!(board.symmetries().len(), 8);
+    }
+
+    #[test]
+    fn cube_board_lines_and_symmetries() {
+        let board = Board::with_layers(4, 4, 4, 4);
+        assert_eq!(board.lines().len(), 76);
+        assert_eq!(board.symmetries().len(), 48);
+    }
+
+    #[test]
+    fn only_exact_runs_win_on_exact_boards() {
+        for exact in [false, true] {
+            let mut board = Board::new(15, 15, 5);
+            if exact {
+                board = board.exact();
+            }
+            for column in [0, 1, 2, 3, 4, 6] {
+                board.make_move(board.index(column, 7).unwrap(), Player::X);
+            }
+            assert!(board.is_winning_move(board.index(4, 7).unwrap(), Player::X));
+
+            // Joining the five with the sixth makes a run too long to count.
+            let joining = board.index(5, 7).unwrap();
+            board.make_move(joining, Player::X);
+            assert_eq!(board.is_winning_move(joining, Player::X), !exact);
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use iced::mouse;
+use iced::widget::canvas::{self, event, path, Event, Frame, Geometry, Path, Stroke};
+use iced::{alignment, Color, Point, Rectangle, Size, Vector};
+
+use tic_tac_toe::{Board, CellState, MetaBoard, Player};
+
+use crate::theme::Theme;
+use crate::widget::Renderer;
+use crate::Message;
+
+// The board drawn as vector graphics: grid lines, marks, the keyboard
+// cursor, a faint mark under the mouse and a line through the winning cells,
+// with the layers of a stacked board side by side. Large boards get their
+// column letters and row numbers around them and a dot on the last move.
+// Ultimate games get thicker lines around the local boards, the boards that
+// can be played lit up and a large mark over the claimed ones. Pressing a
+// cell sends `Message::ButtonPress`.
+pub struct BoardCanvas {
+    pub board: Board,
+    pub meta: Option<MetaBoard>,
+    pub last_move: Option<usize>,
+    // As given by `Game::winning_lines` and `Game::winning_cells`.
+    pub winning_lines: Vec<Vec<usize>>,
+    pub winning_cells: Vec<usize>,
+    pub game_over: bool,
+    // Whether to label the columns and rows and dot the last move.
+    pub coordinates: bool,
+    pub cursor: Option<usize>,
+    // Mark of the human to play, while the board takes moves.
+    pub preview: Option<Player>,
+    // Cell whose mark is being drawn in, and how far along it is, from 0
+    // to 1.
+    pub placing: Option<(usize, f32)>,
+    // How far the line through the winning cells has swept, from 0 to 1.
+    pub sweep: f32,
+    // Sideways offset of the whole board, in cells.
+    pub shake: f32,
+}
+
+// Space left between the layers of a stacked board, in cells.
+const LAYER_GAP: f32 = 0.5;
+
+// Space left above and to the left of a board for its labels, in cells.
+const LABEL_MARGIN: f32 = 0.8;
+
+// Position and size of the square cells within the canvas bounds. The
+// layers of a stacked board are laid out side by side.
+struct Grid {
+    origin: Point,
+    cell: f32,
+    width: usize,
+    height: usize,
+    layers: usize,
+}
+
+impl Grid {
+    // `margin` cells are kept free above and to the left of the board.
+    fn new(board: &Board, size: Size, margin: f32) -> Self {
+        let (width, height, layers) = (board.width(), board.height(), board.layers());
+        let columns = (width * layers) as f32 + LAYER_GAP * (layers - 1) as f32 + margin;
+        let rows = height as f32 + margin;
+        let cell = (size.width / columns).min(size.height / rows);
+        let origin = Point::new(
+            (size.width - cell * columns) / 2.0 + cell * margin,
+            (size.height - cell * rows) / 2.0 + cell * margin,
+        );
+        Grid {
+            origin,
+            cell,
+            width,
+            height,
+            layers,
+        }
+    }
+
+    // Distance from the left of one layer to the left of the next.
+    fn layer_step(&self) -> f32 {
+        (self.width as f32 + LAYER_GAP) * self.cell
+    }
+
+    fn top_left(&self, index: usize) -> Point {
+        let (layer, index) = (index / (self.width * self.height), index % (self.width * self.height));
+        Point::new(
+            self.origin.x + layer as f32 * self.layer_step() + (index % self.width) as f32 * self.cell,
+            self.origin.y + (index / self.width) as f32 * self.cell,
+        )
+    }
+
+    fn center(&self, index: usize) -> Point {
+        let top_left = self.top_left(index);
+        Point::new(top_left.x + self.cell / 2.0, top_left.y + self.cell / 2.0)
+    }
+
+    // Line between two cell corners of `layer`, given as column and row.
+    fn line(&self, layer: usize, from: (usize, usize), to: (usize, usize)) -> Path {
+        let left = self.origin.x + layer as f32 * self.layer_step();
+        let point = |(column, row): (usize, usize)| {
+            Point::new(left + column as f32 * self.cell, self.origin.y + row as f32 * self.cell)
+        };
+        Path::line(point(from), point(to))
+    }
+
+    // Cell under `point`, relative to the canvas.
+    fn index_at(&self, point: Point) -> Option<usize> {
+        let layer = ((point.x - self.origin.x) / self.layer_step()).floor();
+        let column = ((point.x - self.origin.x - layer * self.layer_step()) / self.cell).floor();
+        let row = ((point.y - self.origin.y) / self.cell).floor();
+        if layer < 0.0 || column < 0.0 || row < 0.0 {
+            return None;
+        }
+        if layer >= self.layers as f32 || column >= self.width as f32 || row >= self.height as f32 {
+            return None;
+        }
+        Some((layer as usize * self.height + row as usize) * self.width + column as usize)
+    }
+}
+
+impl BoardCanvas {
+    fn grid(&self, size: Size) -> Grid {
+        Grid::new(&self.board, size, if self.coordinates { LABEL_MARGIN } else { 0.0 })
+    }
+
+    // Empty cell under the mouse, when a move can be played there.
+    fn playable_cell(&self, bounds: Rectangle, cursor: mouse::Cursor) -> Option<usize> {
+        self.preview?;
+        let index = self.grid(bounds.size()).index_at(cursor.position_in(bounds)?)?;
+        let legal = match &self.meta {
+            Some(meta) => meta.is_legal(&self.board, index),
+            None => self.board.is_free(index),
+        };
+        legal.then_some(index)
+    }
+
+    // Draws the first `progress` of a mark filling a square of side `size`:
+    // the strokes of an X one after the other, an O clockwise from the top.
+    fn draw_mark(frame: &mut Frame, center: Point, size: f32, player: Player, color: Color, progress: f32) {
+        let reach = size * 0.3;
+        let stroke = Stroke::default()
+            .with_color(color)
+            .with_width((size * 0.08).max(2.0))
+            .with_line_cap(canvas::LineCap::Round);
+
+        let path = match player {
+            Player::X => Path::new(|builder| {
+                let strokes = [
+                    (Point::new(center.x - reach, center.y - reach), Point::new(center.x + reach, center.y + reach)),
+                    (Point::new(center.x + reach, center.y - reach), Point::new(center.x - reach, center.y + reach)),
+                ];
+                for (stroke, (from, to)) in strokes.into_iter().enumerate() {
+                    let drawn = (progress * 2.0 - stroke as f32).clamp(0.0, 1.0);
+                    if drawn > 0.0 {
+                        builder.move_to(from);
+                        builder.line_to(from + (to - from) * drawn);
+                    }
+                }
+            }),
+            Player::O if progress >= 1.0 => Path::circle(center, reach),
+            Player::O => Path::new(|builder| {
+                builder.arc(path::Arc {
+                    center,
+                    radius: reach,
+                    start_angle: -FRAC_PI_2,
+                    end_angle: -FRAC_PI_2 + TAU * progress,
+                });
+            }),
+        };
+        frame.stroke(&path, stroke);
+    }
+}
+
+impl canvas::Program<Message, Renderer> for BoardCanvas {
+    type State = ();
+
+    fn update(
+        &self,
+        _state: &mut Self::State,
+        event: Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<Message>) {
+        match event {
+            // Presses on taken cells go through too, to be turned down.
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) if self.preview.is_some() => {
+                let grid = self.grid(bounds.size());
+                match cursor.position_in(bounds).and_then(|point| grid.index_at(point)) {
+                    Some(index) => (event::Status::Captured, Some(Message::ButtonPress(index))),
+                    None => (event::Status::Ignored, None),
+                }
+            }
+            _ => (event::Status::Ignored, None),
+        }
+    }
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let palette = theme.palette();
+        let grid = self.grid(bounds.size());
+        let mut frame = Frame::new(renderer, bounds.size());
+        frame.translate(Vector::new(self.shake * grid.cell, 0.0));
+        let line_width = (grid.cell * 0.03).max(1.0);
+        let cell_size = Size::new(grid.cell, grid.cell);
+        let player_color = |player: Player| match player {
+            Player::X => palette.player_x,
+            Player::O => palette.player_o,
+        };
+
+        let active_boards = match &self.meta {
+            Some(meta) if !self.game_over => meta.active_boards(&self.board),
+            _ => Vec::new(),
+        };
+        let active_cells = active_boards.into_iter().flat_map(MetaBoard::local_cells);
+        for index in active_cells.chain(self.winning_cells.iter().copied()) {
+            frame.fill_rectangle(grid.top_left(index), cell_size, palette.surface);
+        }
+
+        // Inner lines only, as on paper, thicker between local boards.
+        let grid_stroke = |line: usize| {
+            let width = if self.meta.is_some() && line.is_multiple_of(3) { line_width * 3.0 } else { line_width };
+            Stroke::default().with_color(palette.accent).with_width(width)
+        };
+        for layer in 0..grid.layers {
+            for column in 1..grid.width {
+                frame.stroke(&grid.line(layer, (column, 0), (column, grid.height)), grid_stroke(column));
+            }
+            for row in 1..grid.height {
+                frame.stroke(&grid.line(layer, (0, row), (grid.width, row)), grid_stroke(row));
+            }
+        }
+
+        // The last move and the cursor are outlined inside their cell.
+        let outline = |frame: &mut Frame, index: usize, color: Color| {
+            let inset = line_width * 2.0;
+            let top_left = grid.top_left(index);
+            let path = Path::rectangle(
+                Point::new(top_left.x + inset, top_left.y + inset),
+                Size::new(grid.cell - 2.0 * inset, grid.cell - 2.0 * inset),
+            );
+            frame.stroke(&path, Stroke::default().with_color(color).with_width(line_width * 2.0));
+        };
+        if let Some(last_move) = self.last_move.filter(|_| !self.game_over) {
+            outline(&mut frame, last_move, palette.highlight);
+        }
+
+        // Named as in `Board::cell_name`.
+        if self.coordinates {
+            let label = |content: String, position: Point| canvas::Text {
+                content,
+                position,
+                color: palette.text,
+                size: grid.cell * 0.5,
+                horizontal_alignment: alignment::Horizontal::Center,
+                vertical_alignment: alignment::Vertical::Center,
+                ..canvas::Text::default()
+            };
+            let offset = grid.cell * LABEL_MARGIN / 2.0;
+            for column in 0..grid.width {
+                let x = grid.center(column).x;
+                let letter = (b'a' + column as u8) as char;
+                frame.fill_text(label(letter.to_string(), Point::new(x, grid.origin.y - offset)));
+            }
+            for row in 0..grid.height {
+                let y = grid.center(row * grid.width).y;
+                frame.fill_text(label((row + 1).to_string(), Point::new(grid.origin.x - offset, y)));
+            }
+        }
+        if let Some(cursor) = self.cursor {
+            outline(&mut frame, cursor, palette.text);
+        }
+
+        let claimed = |index: usize| {
+            let meta = self.meta.as_ref();
+            meta.is_some_and(|meta| !meta.claimed().is_free(MetaBoard::local_board(index)))
+        };
+
+        for (index, state) in self.board.cells().iter().enumerate() {
+            let CellState::Occupied(player) = *state else {
+                continue;
+            };
+            // Once the game is over only the winning line keeps its colours,
+            // and the marks of a claimed local board give way to the large
+            // one.
+            let color = if claimed(index) || self.game_over && !self.winning_cells.contains(&index) {
+                palette.dimmed
+            } else {
+                player_color(player)
+            };
+            let progress = match self.placing {
+                Some((placing, progress)) if placing == index => progress,
+                _ => 1.0,
+            };
+            Self::draw_mark(&mut frame, grid.center(index), grid.cell, player, color, progress);
+        }
+
+        if let Some(meta) = &self.meta {
+            for (local, state) in meta.claimed().cells().iter().enumerate() {
+                if let CellState::Occupied(player) = *state {
+                    let center = grid.center(MetaBoard::local_cells(local)[4]);
+                    Self::draw_mark(&mut frame, center, grid.cell * 3.0, player, player_color(player), 1.0);
+                }
+            }
+        }
+
+        if let (Some(player), Some(index)) = (self.preview, self.playable_cell(bounds, cursor)) {
+            let color = Color { a: 0.35, ..player_color(player) };
+            Self::draw_mark(&mut frame, grid.center(index), grid.cell, player, color, 1.0);
+        }
+
+        if let Some(last_move) = self.last_move.filter(|_| self.coordinates && !self.game_over) {
+            frame.fill(&Path::circle(grid.center(last_move), grid.cell * 0.12), palette.highlight);
+        }
+
+        // Cells of a line are in board order, so its ends are the lowest and
+        // the highest.
+        let strike = Stroke::default()
+            .with_color(palette.highlight)
+            .with_width((grid.cell * 0.1).max(3.0))
+            .with_line_cap(canvas::LineCap::Round);
+        for line in self.winning_lines.iter().filter(|_| self.sweep > 0.0) {
+            if let (Some(first), Some(last)) = (line.iter().min(), line.iter().max()) {
+                let (from, to) = (grid.center(*first), grid.center(*last));
+                let end = from + (to - from) * self.sweep;
+                frame.stroke(&Path::line(from, end), strike.clone());
+            }
+        }
+
+        vec![frame.into_geometry()]
+    }
+
+    fn mouse_interaction(&self, _state: &Self::State, bounds: Rectangle, cursor: mouse::Cursor) -> mouse::Interaction {
+        match self.playable_cell(bounds, cursor) {
+            Some(_) => mouse::Interaction::Pointer,
+            None => mouse::Interaction::default(),
+        }
+    }
+}
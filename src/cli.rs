@@ -0,0 +1,202 @@
+use std::fmt;
+
+use tic_tac_toe::{FirstMove, Level, Mode, Variant};
+
+pub const USAGE: &str = "\
+Usage: tic-tac-toe [OPTIONS]
+
+Options:
+  --mode <MODE>      one-player, two-players or ai-vs-ai [default: two-players]
+  --level <LEVEL>    easy, medium, hard or monte-carlo [default: easy]
+  --first <PLAYER>   who opens a one-player game: human, ai or random [default: human]
+  --board <BOARD>    3x3, 4x4, 5x5, ultimate, qubic, gomoku or
+                     gomoku-standard [default: 3x3]
+  --fullscreen       open full screen
+  --windowed         open in a window
+  --size <WxH>       window size in pixels
+  --no-animations    start with animations turned off
+  -h, --help         print this help
+
+The window mode and size default to those of the last session.
+";
+
+// Startup settings, handed to the application as its flags. The window
+// settings are only set when given, overriding the remembered ones.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Options {
+    pub mode: Mode,
+    pub level: Level,
+    pub first_move: FirstMove,
+    pub variant: Variant,
+    pub fullscreen: Option<bool>,
+    pub size: Option<(u32, u32)>,
+    pub no_animations: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CliError {
+    UnknownOption(String),
+    MissingValue(&'static str),
+    InvalidValue(&'static str, String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::UnknownOption(option) => write!(f, "unknown option {option:?}"),
+            CliError::MissingValue(option) => write!(f, "{option} needs a value"),
+            CliError::InvalidValue(option, value) => write!(f, "invalid value {value:?} for {option}"),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+// Parses the arguments following the program name. Returns `None` when
+// help was asked for.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Options>, CliError> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        // Both "--level hard" and "--level=hard" are accepted.
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) => (name.to_string(), Some(value.to_string())),
+            None => (arg, None),
+        };
+
+        let option = match name.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--fullscreen" | "--windowed" => {
+                options.fullscreen = Some(name == "--fullscreen");
+                continue;
+            }
+            "--no-animations" => {
+                options.no_animations = true;
+                continue;
+            }
+            "--mode" => "--mode",
+            "--level" => "--level",
+            "--first" => "--first",
+            "--board" => "--board",
+            "--size" => "--size",
+            _ => return Err(CliError::UnknownOption(name)),
+        };
+
+        let value = inline_value.or_else(|| args.next()).ok_or(CliError::MissingValue(option))?;
+        let invalid = || CliError::InvalidValue(option, value.clone());
+
+        match option {
+            "--mode" => options.mode = parse_mode(&value).ok_or_else(invalid)?,
+            "--level" => options.level = parse_level(&value).ok_or_else(invalid)?,
+            "--first" => options.first_move = parse_first_move(&value).ok_or_else(invalid)?,
+            "--board" => options.variant = parse_variant(&value).ok_or_else(invalid)?,
+            _ => options.size = Some(parse_size(&value).ok_or_else(invalid)?),
+        }
+    }
+    Ok(Some(options))
+}
+
+fn parse_mode(value: &str) -> Option<Mode> {
+    match value {
+        "one-player" => Some(Mode::OnePlayer),
+        "two-players" => Some(Mode::TwoPlayers),
+        "ai-vs-ai" => Some(Mode::AiVsAi),
+        _ => None,
+    }
+}
+
+fn parse_level(value: &str) -> Option<Level> {
+    match value {
+        "easy" => Some(Level::Easy),
+        "medium" => Some(Level::Medium),
+        "hard" => Some(Level::Hard),
+        "monte-carlo" => Some(Level::MonteCarlo),
+        _ => None,
+    }
+}
+
+fn parse_first_move(value: &str) -> Option<FirstMove> {
+    match value {
+        "human" => Some(FirstMove::Human),
+        "ai" => Some(FirstMove::Ai),
+        "random" => Some(FirstMove::Random),
+        _ => None,
+    }
+}
+
+fn parse_variant(value: &str) -> Option<Variant> {
+    match value {
+        "ultimate" => return Some(Variant::Ultimate),
+        "qubic" => return Some(Variant::Qubic),
+        "gomoku" => return Some(Variant::Gomoku),
+        "gomoku-standard" => return Some(Variant::StandardGomoku),
+        _ => {}
+    }
+    Variant::ALL.into_iter().find(|variant| {
+        let (width, height, _) = variant.dimensions();
+        *variant != Variant::Ultimate
+            && !variant.is_gomoku()
+            && variant.layers() == 1
+            && value == format!("{width}x{height}")
+    })
+}
+
+fn parse_size(value: &str) -> Option<(u32, u32)> {
+    let (width, height) = value.split_once('x')?;
+    let size = (width.parse().ok()?, height.parse().ok()?);
+    (size.0 > 0 && size.1 > 0).then_some(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Option<Options>, CliError> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn values_follow_the_option_or_an_equals_sign() {
+        let options = parse_args(&["--mode", "one-player", "--level=hard", "--board", "qubic", "--size=800x600"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.mode, Mode::OnePlayer);
+        assert_eq!(options.level, Level::Hard);
+        assert_eq!(options.variant, Variant::Qubic);
+        assert_eq!(options.size, Some((800, 600)));
+        assert_eq!(options.fullscreen, None);
+        assert!(!options.no_animations);
+    }
+
+    #[test]
+    fn flags_take_no_value() {
+        let options = parse_args(&["--windowed", "--no-animations", "--first", "ai"]).unwrap().unwrap();
+        assert_eq!(options.fullscreen, Some(false));
+        assert!(options.no_animations);
+        assert_eq!(options.first_move, FirstMove::Ai);
+    }
+
+    #[test]
+    fn missing_value() {
+        assert_eq!(parse_args(&["--level"]), Err(CliError::MissingValue("--level")));
+    }
+
+    #[test]
+    fn invalid_values() {
+        assert_eq!(parse_args(&["--size", "800"]), Err(CliError::InvalidValue("--size", "800".to_string())));
+        assert_eq!(parse_args(&["--size=0x600"]), Err(CliError::InvalidValue("--size", "0x600".to_string())));
+        assert_eq!(parse_args(&["--level", "expert"]), Err(CliError::InvalidValue("--level", "expert".to_string())));
+    }
+
+    #[test]
+    fn unknown_option() {
+        assert_eq!(parse_args(&["--colour=red"]), Err(CliError::UnknownOption("--colour".to_string())));
+    }
+
+    #[test]
+    fn help_stops_parsing() {
+        assert_eq!(parse_args(&["--help", "--bogus"]), Ok(None));
+        assert_eq!(parse_args(&["-h"]), Ok(None));
+    }
+}
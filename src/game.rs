@@ -0,0 +1,543 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use crate::ai::{Engine, Level};
+use crate::board::{Board, Player};
+use crate::ultimate::MetaBoard;
+
+/// Who decides the moves of one side of the game.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Controller {
+    #[default]
+    Human,
+    Ai(Level),
+    /// Moves are fed through `Game::play` by the caller, e.g. from the network.
+    Remote,
+}
+
+impl fmt::Display for Controller {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Controller::Human => write!(f, "Human"),
+            Controller::Ai(level) => write!(f, "AI ({level:?})"),
+            Controller::Remote => write!(f, "Remote"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseControllerError(String);
+
+impl fmt::Display for ParseControllerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown player {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseControllerError {}
+
+/// Parses the names written by `Display`.
+impl FromStr for Controller {
+    type Err = ParseControllerError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "Human" => Ok(Controller::Human),
+            "Remote" => Ok(Controller::Remote),
+            _ => name
+                .strip_prefix("AI (")
+                .and_then(|rest| rest.strip_suffix(')'))
+                .and_then(Level::from_name)
+                .map(Controller::Ai)
+                .ok_or_else(|| ParseControllerError(name.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Mode {
+    OnePlayer,
+    #[default]
+    TwoPlayers,
+    AiVsAi,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 3] = [Mode::OnePlayer, Mode::TwoPlayers, Mode::AiVsAi];
+
+    /// Controllers of X and O, in that order. In single player mode the
+    /// human plays `human` and the AI the other mark.
+    pub fn controllers(self, level: Level, human: Player) -> [Controller; 2] {
+        match (self, human) {
+            (Mode::OnePlayer, Player::X) => [Controller::Human, Controller::Ai(level)],
+            (Mode::OnePlayer, Player::O) => [Controller::Ai(level), Controller::Human],
+            (Mode::TwoPlayers, _) => [Controller::Human, Controller::Human],
+            (Mode::AiVsAi, _) => [Controller::Ai(level), Controller::Ai(level)],
+        }
+    }
+
+    /// Mode and AI level giving these controllers of X and O, if any.
+    pub fn of(controllers: [Controller; 2]) -> Option<(Mode, Option<Level>)> {
+        match controllers {
+            [Controller::Human, Controller::Human] => Some((Mode::TwoPlayers, None)),
+            [Controller::Human, Controller::Ai(level)] | [Controller::Ai(level), Controller::Human] => {
+                Some((Mode::OnePlayer, Some(level)))
+            }
+            [Controller::Ai(x), Controller::Ai(o)] if x == o => Some((Mode::AiVsAi, Some(x))),
+            _ => None,
+        }
+    }
+}
+
+/// Who opens a single player game.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum FirstMove {
+    #[default]
+    Human,
+    Ai,
+    Random,
+}
+
+impl FirstMove {
+    pub const ALL: [FirstMove; 3] = [FirstMove::Human, FirstMove::Ai, FirstMove::Random];
+
+    /// Mark the human plays for this setting. X moves first, so the human
+    /// gets O when the AI opens. `Random` draws again on every call.
+    pub fn human_mark(self) -> Player {
+        match self {
+            FirstMove::Human => Player::X,
+            FirstMove::Ai => Player::O,
+            FirstMove::Random => {
+                if rand::random() {
+                    Player::X
+                } else {
+                    Player::O
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for FirstMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FirstMove::Human => write!(f, "Human"),
+            FirstMove::Ai => write!(f, "AI"),
+            FirstMove::Random => write!(f, "Random"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Variant {
+    #[default]
+    Classic,
+    FourByFour,
+    FiveByFive,
+    /// Nine 3x3 boards in a 3x3 meta-board, see `ultimate`.
+    Ultimate,
+    /// Four stacked 4x4 layers, with lines running through the layers too.
+    Qubic,
+    /// Five in a row on a 15x15 board, where longer runs win too.
+    Gomoku,
+    /// Gomoku where only exactly five in a row wins.
+    StandardGomoku,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 7] = [
+        Variant::Classic,
+        Variant::FourByFour,
+        Variant::FiveByFive,
+        Variant::Ultimate,
+        Variant::Qubic,
+        Variant::Gomoku,
+        Variant::StandardGomoku,
+    ];
+
+    /// Width, height and number of marks in a row needed to win. Ultimate
+    /// games are won with three in a row on each of their 3x3 boards.
+    pub fn dimensions(self) -> (usize, usize, usize) {
+        match self {
+            Variant::Classic => (3, 3, 3),
+            Variant::FourByFour => (4, 4, 4),
+            Variant::FiveByFive => (5, 5, 4),
+            Variant::Ultimate => (9, 9, 3),
+            Variant::Qubic => (4, 4, 4),
+            Variant::Gomoku | Variant::StandardGomoku => (15, 15, 5),
+        }
+    }
+
+    pub fn layers(self) -> usize {
+        match self {
+            Variant::Qubic => 4,
+            _ => 1,
+        }
+    }
+
+    /// Whether runs longer than the win length do not win.
+    pub fn is_exact(self) -> bool {
+        self == Variant::StandardGomoku
+    }
+
+    pub fn is_gomoku(self) -> bool {
+        matches!(self, Variant::Gomoku | Variant::StandardGomoku)
+    }
+
+    pub fn board(self) -> Board {
+        let (width, height, win_length) = self.dimensions();
+        let board = Board::with_layers(width, height, self.layers(), win_length);
+        if self.is_exact() {
+            board.exact()
+        } else {
+            board
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (width, height, win_length) = self.dimensions();
+        match self {
+            Variant::Ultimate => write!(f, "Ultimate"),
+            Variant::Qubic => write!(f, "Qubic {width}x{height}x{}", self.layers()),
+            Variant::Gomoku => write!(f, "Gomoku (free-style)"),
+            Variant::StandardGomoku => write!(f, "Gomoku (standard)"),
+            _ => write!(f, "{width}x{height} ({win_length} in a row)"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Status {
+    #[default]
+    Playing,
+    Draw,
+    Winner,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MoveError {
+    OutOfBounds(usize),
+    Occupied(usize),
+    /// The cell is outside the local board an Ultimate move has to go in.
+    WrongBoard(usize),
+    GameOver,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::OutOfBounds(index) => write!(f, "cell {index} is outside the board"),
+            MoveError::Occupied(index) => write!(f, "cell {index} is already occupied"),
+            MoveError::WrongBoard(index) => write!(f, "cell {index} is not on a board that can be played"),
+            MoveError::GameOver => write!(f, "the game is already over"),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+/// One move of the history: `player` put their mark on cell `index`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Move {
+    pub player: Player,
+    pub index: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Game {
+    controllers: [Controller; 2],
+    status: Status,
+    history: Vec<Move>,
+    /// Undone moves, the next one to redo last.
+    undone: Vec<Move>,
+    board: Board,
+    /// Claimed local boards and the board to play next, in Ultimate games.
+    meta: Option<MetaBoard>,
+    player: Player,
+    engine: Engine,
+    started: SystemTime,
+    ended: Option<SystemTime>,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new(Controller::Human, Controller::Human)
+    }
+}
+
+impl Game {
+    /// X always moves first.
+    pub fn new(x: Controller, o: Controller) -> Self {
+        Game::with_board(&Board::default(), x, o)
+    }
+
+    /// Starts a new game on an empty board shaped like `board`.
+    pub fn with_board(board: &Board, x: Controller, o: Controller) -> Self {
+        Game {
+            controllers: [x, o],
+            status: Status::Playing,
+            history: Vec::new(),
+            undone: Vec::new(),
+            board: board.cleared(),
+            meta: None,
+            player: Player::X,
+            engine: Engine::default(),
+            started: SystemTime::now(),
+            ended: None,
+        }
+    }
+
+    pub fn with_variant(variant: Variant, x: Controller, o: Controller) -> Self {
+        Game {
+            meta: (variant == Variant::Ultimate).then(MetaBoard::new),
+            ..Game::with_board(&variant.board(), x, o)
+        }
+    }
+
+    /// The variant matching the board and rules of this game, if any.
+    pub fn variant(&self) -> Option<Variant> {
+        if self.meta.is_some() {
+            return Some(Variant::Ultimate);
+        }
+        let dimensions = (self.board.width(), self.board.height(), self.board.win_length());
+        Variant::ALL.into_iter().find(|variant| {
+            *variant != Variant::Ultimate
+                && variant.dimensions() == dimensions
+                && variant.layers() == self.board.layers()
+                && variant.is_exact() == self.board.is_exact()
+        })
+    }
+
+    pub fn controller(&self, player: Player) -> Controller {
+        self.controllers[player.index()]
+    }
+
+    pub fn set_controller(&mut self, player: Player, controller: Controller) {
+        self.controllers[player.index()] = controller;
+    }
+
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    pub fn playing_count(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn history(&self) -> &[Move] {
+        &self.history
+    }
+
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    pub fn engine_mut(&mut self) -> &mut Engine {
+        &mut self.engine
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn meta(&self) -> Option<&MetaBoard> {
+        self.meta.as_ref()
+    }
+
+    /// The player to move while the game is running, or the last player
+    /// who moved once it is over.
+    pub fn player(&self) -> Player {
+        self.player
+    }
+
+    pub fn started(&self) -> SystemTime {
+        self.started
+    }
+
+    /// When the last move was played, once the game is over.
+    pub fn ended(&self) -> Option<SystemTime> {
+        self.ended
+    }
+
+    pub(crate) fn set_times(&mut self, started: SystemTime, ended: Option<SystemTime>) {
+        self.started = started;
+        self.ended = ended;
+    }
+
+    pub fn winner(&self) -> Option<Player> {
+        match self.status {
+            Status::Winner => Some(self.player),
+            _ => None,
+        }
+    }
+
+    /// Lines completed by the winner, empty unless the game was won. A
+    /// single move can complete more than one line. In Ultimate games these
+    /// are lines of local boards, given by their middle cells.
+    pub fn winning_lines(&self) -> Vec<Vec<usize>> {
+        let Some(winner) = self.winner() else {
+            return Vec::new();
+        };
+        match &self.meta {
+            Some(meta) => meta
+                .winning_lines(winner)
+                .map(|line| line.iter().map(|&local| MetaBoard::local_cells(local)[4]).collect())
+                .collect(),
+            None => self.board.winning_lines(winner).map(<[usize]>::to_vec).collect(),
+        }
+    }
+
+    /// Cells of the lines completed by the winner, or of the local boards
+    /// on them in Ultimate games.
+    pub fn winning_cells(&self) -> Vec<usize> {
+        let mut cells: Vec<usize> = match &self.meta {
+            Some(_) => self
+                .winning_lines()
+                .into_iter()
+                .flatten()
+                .flat_map(|cell| MetaBoard::local_cells(MetaBoard::local_board(cell)))
+                .collect(),
+            None => self.winning_lines().into_iter().flatten().collect(),
+        };
+        cells.sort_unstable();
+        cells.dedup();
+        cells
+    }
+
+    /// Free cells the side to move may play.
+    pub fn available_moves(&self) -> Vec<usize> {
+        match &self.meta {
+            _ if self.status != Status::Playing => Vec::new(),
+            Some(meta) => meta.available_moves(&self.board),
+            None => self.board.available_moves(),
+        }
+    }
+
+    pub fn is_ai_turn(&self) -> bool {
+        self.status == Status::Playing && matches!(self.controller(self.player), Controller::Ai(_))
+    }
+
+    pub fn is_human_turn(&self) -> bool {
+        self.status == Status::Playing && self.controller(self.player) == Controller::Human
+    }
+
+    pub fn play(&mut self, index: usize) -> Result<Status, MoveError> {
+        if self.status != Status::Playing {
+            return Err(MoveError::GameOver);
+        }
+        if index >= self.board.cell_count() {
+            return Err(MoveError::OutOfBounds(index));
+        }
+        if !self.board.is_free(index) {
+            return Err(MoveError::Occupied(index));
+        }
+        if self.meta.as_ref().is_some_and(|meta| !meta.is_legal(&self.board, index)) {
+            return Err(MoveError::WrongBoard(index));
+        }
+
+        self.undone.clear();
+        Ok(self.apply(index))
+    }
+
+    /// Plays a move already known to be legal.
+    fn apply(&mut self, index: usize) -> Status {
+        self.board.make_move(index, self.player);
+        self.history.push(Move {
+            player: self.player,
+            index,
+        });
+
+        let won = match &mut self.meta {
+            Some(meta) => meta.record(&self.board, index, self.player),
+            None => self.board.is_winning_move(index, self.player),
+        };
+        let blocked = self.meta.as_ref().is_some_and(|meta| meta.available_moves(&self.board).is_empty());
+
+        if won {
+            self.status = Status::Winner;
+        } else if self.history.len() == self.board.cell_count() || blocked {
+            self.status = Status::Draw;
+        } else {
+            self.player = self.player.opponent();
+        }
+
+        if self.status != Status::Playing {
+            self.ended = Some(SystemTime::now());
+        }
+        self.status
+    }
+
+    /// The board as it was after the first `ply` moves of the history.
+    pub fn board_at(&self, ply: usize) -> Board {
+        let mut board = self.board.cleared();
+        for played in self.history.iter().take(ply) {
+            board.make_move(played.index, played.player);
+        }
+        board
+    }
+
+    /// The meta-board of an Ultimate game after the first `ply` moves.
+    pub fn meta_at(&self, ply: usize) -> Option<MetaBoard> {
+        self.meta.as_ref()?;
+        let mut board = self.board.cleared();
+        let mut meta = MetaBoard::new();
+        for played in self.history.iter().take(ply) {
+            board.make_move(played.index, played.player);
+            meta.record(&board, played.index, played.player);
+        }
+        Some(meta)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.history.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Takes back the last move. The position is rebuilt by replaying the
+    /// remaining history from an empty board.
+    pub fn undo(&mut self) -> Option<Move> {
+        let last = self.history.pop()?;
+        self.undone.push(last);
+
+        let history = std::mem::take(&mut self.history);
+        self.board = self.board.cleared();
+        self.meta = self.meta.as_ref().map(|_| MetaBoard::new());
+        self.player = Player::X;
+        self.status = Status::Playing;
+        self.ended = None;
+        for played in history {
+            self.apply(played.index);
+        }
+        Some(last)
+    }
+
+    pub fn redo(&mut self) -> Option<Move> {
+        let next = self.undone.pop()?;
+        self.apply(next.index);
+        Some(next)
+    }
+
+    /// Move the AI controlling the side to move would play, if any.
+    pub fn ai_move(&self) -> Option<usize> {
+        match self.controller(self.player) {
+            Controller::Ai(level) if self.status == Status::Playing => match &self.meta {
+                Some(meta) => self.engine.choose_ultimate_move(level, &self.board, meta, self.player),
+                None if self.variant().is_some_and(Variant::is_gomoku) => {
+                    self.engine.choose_gomoku_move(level, &self.board, self.player)
+                }
+                None => self.engine.choose_move(level, &self.board, self.player),
+            },
+            _ => None,
+        }
+    }
+
+    pub fn play_ai(&mut self) -> Option<Result<Status, MoveError>> {
+        self.ai_move().map(|index| self.play(index))
+    }
+}
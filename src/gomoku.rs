@@ -0,0 +1,342 @@
+//! Gomoku positions for the searches in `search` and `mcts`, whose 15x15
+//! board is far too large to try every free cell. Positions are scored by
+//! their threats: every run of five cells still open for a single side
+//! counts for it, the more the closer it is to complete, with fours (one
+//! mark short) and threes (two short) weighing the most. A four has to be
+//! answered at once, so the search then only tries the cells completing or
+//! blocking it. Otherwise it only tries the most promising cells near the
+//! marks already played.
+
+use std::cmp::Reverse;
+
+use crate::board::{Board, Player};
+use crate::mcts::Position;
+use crate::search::{Searchable, WIN_SCORE};
+
+/// Cells tried at each node, the most promising first.
+const BREADTH: usize = 12;
+
+/// Moves are only looked for this many cells away from a mark, at most.
+const REACH: usize = 2;
+
+/// Score for the side to move holding a four, or facing two of them: all but
+/// won or lost, though not found to be forced.
+const THREAT_SCORE: i32 = WIN_SCORE / 4;
+
+/// Worth of a line holding `count` marks of a single side.
+fn threat_weight(count: usize, win_length: usize) -> i32 {
+    if count + 1 >= win_length {
+        10_000
+    } else if count + 2 == win_length {
+        500
+    } else {
+        1 << (2 * count)
+    }
+}
+
+/// Score of a line from the point of view of X, given the marks of each side
+/// on it.
+fn line_score(counts: [usize; 2], win_length: usize) -> i32 {
+    match counts {
+        [x, 0] if x > 0 => threat_weight(x, win_length),
+        [0, o] if o > 0 => -threat_weight(o, win_length),
+        _ => 0,
+    }
+}
+
+fn is_four(counts: [usize; 2], player: Player, win_length: usize) -> bool {
+    counts[player.index()] + 1 == win_length && counts[player.opponent().index()] == 0
+}
+
+/// Cells of the same layer at most `REACH` cells away from `position`,
+/// including itself.
+fn neighbours(board: &Board, position: usize) -> impl Iterator<Item = usize> + '_ {
+    let (column, row) = board.coordinates(position);
+    let layer = board.layer(position);
+    (row.saturating_sub(REACH)..=row + REACH).flat_map(move |row| {
+        (column.saturating_sub(REACH)..=column + REACH)
+            .filter_map(move |column| board.index_in_layer(column, row, layer))
+    })
+}
+
+/// Counts, for every cell, the marks within `REACH` of it.
+fn near_counts(board: &Board) -> Vec<usize> {
+    let mut near = vec![0; board.cell_count()];
+    for position in (0..board.cell_count()).filter(|&position| !board.is_free(position)) {
+        for cell in neighbours(board, position) {
+            near[cell] += 1;
+        }
+    }
+    near
+}
+
+/// Free cells near a mark, the middle of an empty board, or any free cell
+/// once there is none left near the marks.
+fn nearby_cells(board: &Board, near: &[usize]) -> Vec<usize> {
+    if board.played_count() == 0 {
+        return board.index(board.width() / 2, board.height() / 2).into_iter().collect();
+    }
+    let cells: Vec<usize> = (0..board.cell_count())
+        .filter(|&cell| near[cell] > 0 && board.is_free(cell))
+        .collect();
+    if cells.is_empty() {
+        board.available_moves()
+    } else {
+        cells
+    }
+}
+
+/// A Gomoku board for the tree search in `mcts`, whose moves are limited to
+/// the cells near the marks played, as in `ThreatBoard`.
+#[derive(Debug, Clone)]
+pub struct GomokuPosition {
+    board: Board,
+    near: Vec<usize>,
+}
+
+impl GomokuPosition {
+    pub fn new(board: &Board) -> Self {
+        GomokuPosition {
+            board: board.clone(),
+            near: near_counts(board),
+        }
+    }
+}
+
+impl Position for GomokuPosition {
+    fn available_moves(&self) -> Vec<usize> {
+        nearby_cells(&self.board, &self.near)
+    }
+
+    fn play(&mut self, index: usize, player: Player) -> bool {
+        self.board.make_move(index, player);
+        for cell in neighbours(&self.board, index) {
+            self.near[cell] += 1;
+        }
+        self.board.is_winning_move(index, player)
+    }
+}
+
+/// A Gomoku board for the alpha-beta search in `search`, keeping the marks of
+/// each side on every line up to date so that threats are found without
+/// scanning the board.
+#[derive(Debug, Clone)]
+pub struct ThreatBoard {
+    board: Board,
+    /// Marks of each player on every line.
+    counts: Vec<[usize; 2]>,
+    /// Lines that are fours for each player.
+    fours: [usize; 2],
+    /// Sum of the line scores, from the point of view of X.
+    score: i32,
+    near: Vec<usize>,
+}
+
+impl ThreatBoard {
+    pub fn new(board: &Board) -> Self {
+        let mut threats = ThreatBoard {
+            board: board.cleared(),
+            counts: vec![[0; 2]; board.lines().len()],
+            fours: [0; 2],
+            score: 0,
+            near: vec![0; board.cell_count()],
+        };
+        for player in Player::ALL {
+            for position in board.played_moves(player) {
+                threats.play(position, player);
+            }
+        }
+        threats
+    }
+
+    /// Accounts for a mark of `player` put on or taken off `spot`.
+    fn update(&mut self, spot: usize, player: Player, placed: bool) {
+        let win_length = self.board.win_length();
+        for &line in self.board.line_indices(spot) {
+            let counts = &mut self.counts[line];
+            self.score -= line_score(*counts, win_length);
+            for side in Player::ALL {
+                self.fours[side.index()] -= usize::from(is_four(*counts, side, win_length));
+            }
+
+            if placed {
+                counts[player.index()] += 1;
+            } else {
+                counts[player.index()] -= 1;
+            }
+
+            self.score += line_score(*counts, win_length);
+            for side in Player::ALL {
+                self.fours[side.index()] += usize::from(is_four(*counts, side, win_length));
+            }
+        }
+
+        for cell in neighbours(&self.board, spot) {
+            if placed {
+                self.near[cell] += 1;
+            } else {
+                self.near[cell] -= 1;
+            }
+        }
+    }
+
+    /// Free cells where `player` would win at once.
+    fn threat_cells(&mut self, player: Player) -> Vec<usize> {
+        if self.fours[player.index()] == 0 {
+            return Vec::new();
+        }
+
+        let win_length = self.board.win_length();
+        let mut cells: Vec<usize> = (0..self.counts.len())
+            .filter(|&line| is_four(self.counts[line], player, win_length))
+            .filter_map(|line| self.board.lines()[line].iter().copied().find(|&pos| self.board.is_free(pos)))
+            .collect();
+        cells.sort_unstable();
+        cells.dedup();
+        // A four can still fall through on an exact board, by making a run
+        // that is too long.
+        cells.retain(|&cell| {
+            self.board.make_move(cell, player);
+            let won = self.board.is_winning_move(cell, player);
+            self.board.clear(cell);
+            won
+        });
+        cells
+    }
+
+    /// How much playing `cell` would extend the lines of `player` and cut
+    /// those of the opponent.
+    fn potential(&self, cell: usize, player: Player) -> i32 {
+        let win_length = self.board.win_length();
+        self.board
+            .line_indices(cell)
+            .iter()
+            .map(|&line| {
+                let counts = self.counts[line];
+                match (counts[player.index()], counts[player.opponent().index()]) {
+                    (0, 0) => 1,
+                    (mine, 0) => threat_weight(mine + 1, win_length) * 2,
+                    (0, theirs) => threat_weight(theirs + 1, win_length),
+                    _ => 0,
+                }
+            })
+            .sum()
+    }
+}
+
+impl Searchable for ThreatBoard {
+    fn board(&self) -> &Board {
+        &self.board
+    }
+
+    fn play(&mut self, spot: usize, player: Player) {
+        self.board.make_move(spot, player);
+        self.update(spot, player, true);
+    }
+
+    fn undo(&mut self, spot: usize, player: Player) {
+        self.board.clear(spot);
+        self.update(spot, player, false);
+    }
+
+    /// A winning cell, else the cells blocking the opponent's fours, else
+    /// the most promising cells near the marks.
+    fn moves(&mut self, player: Player) -> Vec<usize> {
+        let wins = self.threat_cells(player);
+        if let Some(&win) = wins.first() {
+            return vec![win];
+        }
+        let blocks = self.threat_cells(player.opponent());
+        if !blocks.is_empty() {
+            return blocks;
+        }
+
+        let mut moves: Vec<(i32, usize)> = nearby_cells(&self.board, &self.near)
+            .into_iter()
+            .map(|cell| (self.potential(cell, player), cell))
+            .collect();
+        moves.sort_by_key(|&(potential, _)| Reverse(potential));
+        moves.truncate(BREADTH);
+        moves.into_iter().map(|(_, cell)| cell).collect()
+    }
+
+    fn evaluate(&mut self, player: Player) -> i32 {
+        if !self.threat_cells(player).is_empty() {
+            return THREAT_SCORE;
+        }
+        if self.threat_cells(player.opponent()).len() > 1 {
+            return -THREAT_SCORE;
+        }
+        match player {
+            Player::X => self.score,
+            Player::O => -self.score,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Variant;
+
+    fn cell(board: &Board, column: usize, row: usize) -> usize {
+        board.index(column, row).unwrap()
+    }
+
+    fn assert_same(threats: &ThreatBoard, fresh: &ThreatBoard) {
+        assert_eq!(threats.counts, fresh.counts);
+        assert_eq!(threats.fours, fresh.fours);
+        assert_eq!(threats.score, fresh.score);
+        assert_eq!(threats.near, fresh.near);
+    }
+
+    #[test]
+    fn threats_are_kept_up_to_date() {
+        let board = Variant::Gomoku.board();
+        let mut threats = ThreatBoard::new(&board);
+        let moves = [(7, 7), (8, 8), (7, 8), (6, 6), (7, 9), (9, 9), (7, 10), (0, 0), (14, 14)];
+        let mut player = Player::X;
+        for &(column, row) in &moves {
+            threats.play(cell(&board, column, row), player);
+            player = player.opponent();
+        }
+        assert_same(&threats, &ThreatBoard::new(threats.board()));
+        assert_eq!(threats.fours, [2, 0]);
+
+        for &(column, row) in moves[5..].iter().rev() {
+            player = player.opponent();
+            threats.undo(cell(&board, column, row), player);
+        }
+        assert_same(&threats, &ThreatBoard::new(threats.board()));
+        assert_eq!(threats.board().played_count(), 5);
+    }
+
+    #[test]
+    fn fours_are_completed_or_blocked() {
+        let board = Variant::Gomoku.board();
+        let mut threats = ThreatBoard::new(&board);
+        for row in 5..9 {
+            threats.play(cell(&board, 7, row), Player::X);
+        }
+        threats.play(cell(&board, 7, 4), Player::O);
+
+        assert_eq!(threats.moves(Player::X), vec![cell(&board, 7, 9)]);
+        assert_eq!(threats.moves(Player::O), vec![cell(&board, 7, 9)]);
+    }
+
+    #[test]
+    fn overlines_are_no_threat_on_standard_boards() {
+        for variant in [Variant::Gomoku, Variant::StandardGomoku] {
+            let board = variant.board();
+            let mut threats = ThreatBoard::new(&board);
+            for column in [3, 4, 5, 6, 8] {
+                threats.play(cell(&board, column, 7), Player::X);
+            }
+            threats.play(cell(&board, 2, 7), Player::O);
+
+            // Filling the gap at 7 makes six in a row.
+            let expected = if variant.is_exact() { Vec::new() } else { vec![cell(&board, 7, 7)] };
+            assert_eq!(threats.threat_cells(Player::X), expected);
+        }
+    }
+}
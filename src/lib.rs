@@ -0,0 +1,20 @@
+//! Headless Tic Tac Toe engine: board, move validation, win/draw detection
+//! and the computer players used by the Iced front end.
+
+pub mod ai;
+pub mod board;
+pub mod game;
+pub mod gomoku;
+pub mod mcts;
+pub mod notation;
+pub mod scoreboard;
+pub mod search;
+pub mod transposition;
+pub mod ultimate;
+
+pub use ai::{Engine, Level};
+pub use board::{Board, CellState, Player};
+pub use game::{Controller, FirstMove, Game, Mode, Move, MoveError, Status, Variant};
+pub use notation::NotationError;
+pub use scoreboard::{Scoreboard, Tally};
+pub use ultimate::MetaBoard;
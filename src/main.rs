@@ -1,595 +1,1246 @@
-use rand;
-use rand::seq::SliceRandom; 
-
-use iced::widget::{Text, row};
-use iced::{executor, Application, Command, Length, Settings};
+use iced::widget::{Canvas, Text, row};
+use iced::{executor, Application, Command, Event, Length, Settings, Subscription};
+use iced::keyboard::{self, KeyCode, Modifiers};
 use iced::window;
-use widget::{Row, Column, Renderer, Button, Container, Radio};
-
+use std::f32::consts::TAU;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use widget::{Row, Column, Renderer, Button, Container, Radio, Scrollable, Slider, TextInput};
+
+use tic_tac_toe::mcts::{Budget, DEFAULT_ITERATIONS};
+use tic_tac_toe::{notation, Controller, FirstMove, Game, Level, Mode, Player, Scoreboard, Status, Tally, Variant};
+
+use self::board_canvas::BoardCanvas;
+use self::cli::Options;
+use self::palette::Palette;
 use self::theme::Theme;
+use self::window_state::WindowState;
 use self::widget::Element;
 
-const BUTTON_SIZE: u16 = 200;
-const TEXT_SIZE: u16 = ((BUTTON_SIZE as f64) * 0.8) as u16;
+mod board_canvas;
+mod cli;
+mod palette;
+mod window_state;
+
+const PLACE_DURATION: Duration = Duration::from_millis(250);
+const SWEEP_DURATION: Duration = Duration::from_millis(400);
+const SHAKE_DURATION: Duration = Duration::from_millis(300);
 
 fn main() {
+    let options = match cli::parse(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            print!("{}", cli::USAGE);
+            return;
+        }
+        Err(error) => {
+            eprintln!("error: {error}\n\n{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let mut window = WindowState::path().map(|path| WindowState::load(&path)).unwrap_or_default();
+    window.size = options.size.unwrap_or(window.size);
+    window.fullscreen = options.fullscreen.unwrap_or(window.fullscreen);
+
     let settings = Settings {
         window: iced::window::Settings {
             resizable: true,
-            size: (1200, 1200),
+            size: window.size,
+            position: match window.position {
+                Some((x, y)) => window::Position::Specific(x, y),
+                None => window::Position::Default,
+            },
             ..Default::default()
         },
-        ..Default::default()
+        // The window state is saved before closing.
+        exit_on_close_request: false,
+        ..Settings::with_flags((options, window))
     };
 
     TicTacToe::run(settings).unwrap();
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum Player {
-    X,
-    O,
-    Human,
-    AI,
+// Suggested location for saved games.
+fn default_game_path() -> PathBuf {
+    dirs::document_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_default()
+        .join("tic-tac-toe-game.txt")
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum CellState {
-    Empty,
-    Occupied(Player),
+// Where the scoreboard is kept between sessions.
+fn scoreboard_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("tic-tac-toe-iced").join("scoreboard.txt"))
 }
 
-#[derive(Debug, Clone, Copy)]
-struct  Cell {
-    state: CellState,
-    background: theme::Background,
-    color: theme::Color
+// Start times of the board animations, which run until their duration has
+// passed.
+#[derive(Debug, Clone, Copy, Default)]
+struct Animations {
+    // The last mark placed, drawn in stroke by stroke.
+    placed: Option<(usize, Instant)>,
+    // The line through the winning cells, swept once the mark is drawn.
+    won: Option<Instant>,
+    // The board shaking after a press on a taken cell.
+    shaken: Option<Instant>,
 }
 
-impl Default for Cell {
-    fn default() -> Self {
-        Cell { 
-            state: CellState::Empty, 
-            background: theme::Background::default(),
-            color: theme::Color::default() 
-        }
+impl Animations {
+    fn is_running(&self, now: Instant) -> bool {
+        let placed = self.placed.map(|(_, started)| started + PLACE_DURATION);
+        let won = self.won.map(|started| started + SWEEP_DURATION);
+        let shaken = self.shaken.map(|started| started + SHAKE_DURATION);
+        [placed, won, shaken].into_iter().flatten().any(|end| end > now)
     }
 }
 
+// How far an animation started at `started` is at `now`, from 0 to 1.
+fn progress(started: Instant, duration: Duration, now: Instant) -> f32 {
+    (now.saturating_duration_since(started).as_secs_f32() / duration.as_secs_f32()).min(1.0)
+}
+
+// A saved game being played back move by move.
+#[derive(Debug, Clone)]
+struct Replay {
+    game: Game,
+    // Number of moves shown so far.
+    shown: usize,
+    playing: bool,
+}
 
 #[derive(Debug, Clone)]
-struct  Board {
-    cells: [Cell; 9],
+struct TicTacToe {
+    game: Game,
+    mode: Mode,
+    level: Level,
+    first_move: FirstMove,
+    variant: Variant,
+    playouts: u32,
+    // Minimum time the AI takes to reply, in milliseconds.
+    ai_delay: u32,
+    // Identifies the position the AI was last asked about, so that replies
+    // computed before a restart or an undo are ignored.
+    request_id: u64,
+    // Raised to stop the search answering the last request.
+    ai_stop: Arc<AtomicBool>,
+    thinking: bool,
+    // Number of moves played in the past position shown from the history
+    // panel, or `None` while showing the live game.
+    viewing: Option<usize>,
+    scoreboard: Scoreboard,
+    // Whether the current game's result is already on the scoreboard, so
+    // that undoing and finishing it again does not count it twice.
+    scored: bool,
+    game_path: String,
+    window: WindowState,
+    // Current size of the window, full screen too, which the controls are
+    // scaled to.
+    viewport: (u32, u32),
+    replay: Option<Replay>,
+    // Time between two replayed moves, in milliseconds.
+    replay_delay: u32,
+    // Identifies the pending replay tick, like `request_id` for AI replies.
+    replay_id: u64,
+    // Cell picked with the keyboard, shown once a cursor key was pressed.
+    cursor: Option<usize>,
+    // The built-in themes followed by those found in `palette::themes_dir`.
+    themes: Vec<(String, Palette)>,
+    // Index of the theme in use.
+    theme: usize,
+    // Whether moves, wins and refused presses are animated.
+    animate: bool,
+    animations: Animations,
+    // Time of the last animation frame.
+    now: Instant,
+    // Outcome of the last save or open.
+    file_status: String,
+    message: String,
 }
 
-impl Default for Board {
+impl Default for TicTacToe {
     fn default() -> Self {
-        Board { 
-            cells: [Cell::default(); 9], 
-        }
+        let mut tic_tac_toe = TicTacToe {
+            game: Game::default(),
+            mode: Mode::default(),
+            level: Level::default(),
+            first_move: FirstMove::default(),
+            variant: Variant::default(),
+            playouts: DEFAULT_ITERATIONS,
+            ai_delay: 0,
+            request_id: 0,
+            ai_stop: Arc::default(),
+            thinking: false,
+            viewing: None,
+            scoreboard: Scoreboard::default(),
+            scored: false,
+            game_path: default_game_path().display().to_string(),
+            window: WindowState::default(),
+            viewport: WindowState::default().size,
+            replay: None,
+            replay_delay: 800,
+            replay_id: 0,
+            cursor: None,
+            themes: Palette::BUILT_IN.iter().map(|(name, palette)| (name.to_string(), *palette)).collect(),
+            theme: 0,
+            animate: true,
+            animations: Animations::default(),
+            now: Instant::now(),
+            file_status: String::new(),
+            message: String::new(),
+        };
+        tic_tac_toe.update_message();
+        tic_tac_toe
     }
 }
 
-impl Board {
-    fn make_move(&mut self, position: usize, player: Player) {
-        self.cells[position].state = CellState::Occupied(player);
+impl TicTacToe {
+    fn new_game(&mut self) -> Command<Message> {
+        let [x, o] = self.mode.controllers(self.level, self.first_move.human_mark());
+
+        self.cancel_ai();
+        self.game = Game::with_variant(self.variant, x, o);
+        self.game.engine_mut().mcts.budget = Budget::Iterations(self.playouts);
+        self.viewing = None;
+        self.scored = false;
+        self.cursor = self.cursor.filter(|&cursor| cursor < self.game.board().cells().len());
+        self.next_turn()
     }
 
-    fn available_moves(&self) -> Vec<usize> {
-        self.cells
-            .iter()
-            .enumerate()
-            .filter_map(|(i, &cell)| if cell.state == CellState::Empty { Some(i) } else { None })
-            .collect()
+    fn side_name(&self, player: Player) -> String {
+        match self.game.controller(player) {
+            Controller::Human if self.mode == Mode::TwoPlayers => player.to_string(),
+            Controller::Human => format!("Human ({player})"),
+            Controller::Ai(_) => format!("AI ({player})"),
+            Controller::Remote => format!("Remote ({player})"),
+        }
     }
 
-    #[allow(dead_code)]    
-    fn ai_played_moves(&self) -> Vec<usize> {
-        self.cells
-            .iter()
-            .enumerate()
-            .filter_map(|(i, &cell)| if cell.state == CellState::Occupied(Player::AI) { Some(i) } else { None })
-            .collect()
-    }
+    fn update_message(&mut self) {
+        let side = self.side_name(self.game.player());
 
-    fn hu_played_moves(&self) -> Vec<usize> {
-        self.cells
-            .iter()
-            .enumerate()
-            .filter_map(|(i, &cell)| if cell.state == CellState::Occupied(Player::Human) { Some(i) } else { None })
-            .collect()
+        self.message = match self.game.status() {
+            Status::Playing if self.thinking => format!("{side} is thinking…"),
+            Status::Winner => format!("{side} is the winner!!!!"),
+            Status::Draw => "We have a draw.".to_string(),
+            Status::Playing => format!("{side} turn."),
+        };
     }
 
-    fn check_win(&self, player: Player) -> bool {
-        let win_combos = [
-            [0, 1, 2],
-            [3, 4, 5],
-            [6, 7, 8],
-            [0, 3, 6],
-            [1, 4, 7],
-            [2, 5, 8],
-            [0, 4, 8],
-            [6, 4, 2],
-        ];
+    // Starts the AI reply when it is its turn, scores a finished game and
+    // refreshes the status line.
+    fn next_turn(&mut self) -> Command<Message> {
+        if !self.scored && self.game.status() != Status::Playing {
+            self.scored = true;
+            if self.scoreboard.record(&self.game) {
+                self.save_scoreboard();
+            }
+        }
 
-        win_combos
-            .iter()
-            .any(|&combo| combo.iter().all(|&pos| self.cells[pos].state == CellState::Occupied(player)))
+        let command = if self.game.is_ai_turn() {
+            self.request_ai_move()
+        } else {
+            Command::none()
+        };
+        self.update_message();
+        command
     }
 
+    // Steps back through the history until it is a human's turn again, so
+    // that in single player mode the AI reply goes with the human move.
+    fn undo(&mut self) -> Command<Message> {
+        self.cancel_ai();
+        self.viewing = None;
+        while self.game.undo().is_some() && !self.game.is_human_turn() {}
+        self.next_turn()
+    }
 
-    
-}
-
-
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
-enum Mode {
-    OnePlayer,
-    #[default]   
-    TwoPlayers,
-}
+    fn redo(&mut self) -> Command<Message> {
+        self.cancel_ai();
+        self.viewing = None;
+        while self.game.redo().is_some() && !self.game.is_human_turn() {}
+        self.next_turn()
+    }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
-enum Level {
-    #[default]
-    Easy,
-    Medium,
-    Hard,
-}
+    // Continues a game read from a file, with the settings matching it.
+    fn load_game(&mut self, game: Game) -> Command<Message> {
+        self.cancel_ai();
+        self.game = game;
+        self.game.engine_mut().mcts.budget = Budget::Iterations(self.playouts);
 
+        if let Some((mode, level)) = Mode::of([self.game.controller(Player::X), self.game.controller(Player::O)]) {
+            self.mode = mode;
+            self.level = level.unwrap_or(self.level);
+        }
+        if let Some(variant) = self.game.variant() {
+            self.variant = variant;
+        }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
-enum Status {
-    #[default]
-    Playing,
-    Draw,
-    Winner
-}
+        self.viewing = None;
+        // Games played elsewhere do not go on the scoreboard.
+        self.scored = true;
+        self.cursor = self.cursor.filter(|&cursor| cursor < self.game.board().cells().len());
+        self.next_turn()
+    }
 
+    fn save_game(&mut self) {
+        self.file_status = match std::fs::write(&self.game_path, notation::write(&self.game)) {
+            Ok(()) => format!("Saved to {}", self.game_path),
+            Err(error) => format!("Could not save: {error}"),
+        };
+    }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-struct  Game {
-    mode: Mode,
-    level: Option<Level>,
-    status: Status,
-    playing_count: usize
-}
+    fn read_game_file(&self) -> Result<Game, String> {
+        let text = std::fs::read_to_string(&self.game_path).map_err(|error| error.to_string())?;
+        notation::read(&text).map_err(|error| error.to_string())
+    }
 
-impl Default for Game {
-    fn default() -> Self {
-        Self { 
-            mode: Mode::TwoPlayers, 
-            level: Some(Level::Easy), 
-            status: Default::default(), 
-            playing_count: Default::default() 
+    fn open_game(&mut self) -> Command<Message> {
+        match self.read_game_file() {
+            Ok(game) => {
+                self.file_status = format!("Opened {}", self.game_path);
+                self.load_game(game)
+            }
+            Err(error) => {
+                self.file_status = format!("Could not open: {error}");
+                Command::none()
+            }
         }
     }
-}
 
+    fn replay_game(&mut self) -> Command<Message> {
+        match self.read_game_file() {
+            Ok(game) => {
+                self.file_status = format!("Replaying {}", self.game_path);
+                // A game without moves has nothing to play.
+                let playing = game.playing_count() > 0;
+                self.replay = Some(Replay { game, shown: 0, playing });
+                if !playing {
+                    return Command::none();
+                }
+                self.schedule_replay_tick()
+            }
+            Err(error) => {
+                self.file_status = format!("Could not open: {error}");
+                Command::none()
+            }
+        }
+    }
 
-#[derive(Debug, Clone, Copy)]
-struct Move {
-    score: i32,
-    index: usize,
-}
+    fn schedule_replay_tick(&mut self) -> Command<Message> {
+        self.replay_id += 1;
+        let replay_id = self.replay_id;
+        let delay = Duration::from_millis(self.replay_delay.into());
 
+        Command::perform(async move { std::thread::sleep(delay) }, move |_| Message::ReplayTick(replay_id))
+    }
 
-#[derive(Debug, Clone)]
-struct TicTacToe {
-    board: Board,
-    game: Game,
-    message: String,
-    player: Player,
-}
+    fn replay_tick(&mut self) -> Command<Message> {
+        let Some(replay) = self.replay.as_mut().filter(|replay| replay.playing) else {
+            return Command::none();
+        };
 
-impl Default for TicTacToe {
-    fn default() -> Self {
-        TicTacToe {
-            board: Board::default(),
-            game: Game::default(),
-            message: "X turn.".to_string(),
-            player: Player::X,
+        if replay.shown >= replay.game.playing_count() {
+            replay.playing = false;
+            return Command::none();
         }
-    }
-}
 
-impl TicTacToe {    
-    fn  check_for_winner(&mut self) -> bool {
-        if self.board.check_win(self.player) {
-            self.message = format!("{} is the winner!!!!", match self.player {
-                Player::X => "X",
-                Player::O => "O",
-                Player::AI => "AI",
-                Player::Human => "Human",
-            });
-    
-            self.game.status = Status::Winner;
-            return true;
+        replay.shown += 1;
+        let index = replay.game.history()[replay.shown - 1].index;
+        let complete = replay.shown >= replay.game.playing_count();
+        let won = complete && replay.game.status() == Status::Winner;
+        if complete {
+            replay.playing = false;
         }
 
-        if self.game.playing_count == 9 {
-            self.message = "We have a draw.".to_string();
-            self.game.status = Status::Draw;
-            return true
+        self.animate_move(index, won);
+        if complete {
+            return Command::none();
         }
+        self.schedule_replay_tick()
+    }
 
-        self.message = format!("{} turn.", match self.player {
-            Player::X => "O",
-            Player::O => "X",
-            Player::AI => "AI",
-            Player::Human => "Human",
-            
-        });
-
-        return  false;
+    fn toggle_replay(&mut self) -> Command<Message> {
+        let Some(replay) = self.replay.as_mut() else {
+            return Command::none();
+        };
 
-        
+        if replay.playing || replay.game.playing_count() == 0 {
+            replay.playing = false;
+            self.replay_id += 1;
+            return Command::none();
+        }
+        if replay.shown == replay.game.playing_count() {
+            replay.shown = 0;
+        }
+        replay.playing = true;
+        self.schedule_replay_tick()
     }
 
-    fn make_ai_move(&mut self, index: usize) {
+    // Pauses the replay and shows `shown` moves.
+    fn step_replay(&mut self, shown: usize) {
+        if let Some(replay) = self.replay.as_mut() {
+            replay.playing = false;
+            replay.shown = shown.min(replay.game.playing_count());
+            self.replay_id += 1;
+        }
+    }
 
-        if self.board.cells[index].state == CellState::Empty && Status::Playing == self.game.status {
-            self.board.cells[index].color = theme::Color::AI;             
-            self.board.cells[index].state = CellState::Occupied(Player::AI);
-            self.game.playing_count += 1;
-            
-            if !self.check_for_winner() {
-                self.player = Player::Human;
-                self.check_for_winner();
-    
+    fn save_scoreboard(&self) {
+        if let Some(path) = scoreboard_path() {
+            if let Err(error) = self.scoreboard.save(&path) {
+                eprintln!("could not save the scoreboard to {}: {error}", path.display());
             }
         }
     }
 
-    fn button_handler(&mut self, index: usize) {
-        if self.board.cells[index].state == CellState::Empty && Status::Playing == self.game.status {
-            let cell_state = match self.player {
-                Player::X => { 
-                    self.board.cells[index].color = theme::Color::PlayerX;
-                    CellState::Occupied(Player::X) 
-                },
-                Player::O => { 
-                    self.board.cells[index].color = theme::Color::PlayerO;
-                    CellState::Occupied(Player::O) 
-                },
-                Player::AI => { 
-                    self.board.cells[index].color = theme::Color::AI;
-                    CellState::Occupied(Player::AI) 
-                },
-                Player::Human => { 
-                    self.board.cells[index].color = theme::Color::Human;
-                    CellState::Occupied(Player::Human) 
-                },
-            };
-            self.board.cells[index].state = cell_state;
-            self.game.playing_count += 1;
-
-            self.check_for_winner();
-            self.player = match self.player {
-                Player::X => Player::O,
-                Player::O => Player::X,
-                Player::AI => Player::Human,
-                Player::Human => Player::AI,
-            };
-
-            if self.game.mode == Mode::OnePlayer && self.game.status == Status::Playing {
-                let ai_move: usize;
-                match self.game.level  {
-                    Some(Level::Easy) => {
-                        ai_move = Self::free_spot(&self.board);                        
-                    }
-                    Some(Level::Medium) => {
-                        ai_move = Self::closest_spot(&self.board, &self.game);                        
+    fn handle_shortcut(&mut self, shortcut: Shortcut) -> Command<Message> {
+        let board = self.game.board();
+        let (width, height, layers) = (board.width(), board.height(), board.layers());
+        // The board only takes moves while it shows the live game.
+        let live = self.replay.is_none() && self.viewing.is_none();
+
+        match shortcut {
+            Shortcut::MoveCursor(dx, dy) => {
+                // The first press shows the cursor in the middle of the board.
+                // The layers of a stacked board are side by side, so the
+                // cursor walks from one into the next.
+                self.cursor = Some(match self.cursor {
+                    Some(cursor) => {
+                        let (column, row) = board.coordinates(cursor);
+                        let column = (board.layer(cursor) * width + column)
+                            .saturating_add_signed(dx)
+                            .min(width * layers - 1);
+                        let row = row.saturating_add_signed(dy).min(height - 1);
+                        (column / width * height + row) * width + column % width
                     }
-                    Some(Level::Hard) => {
-                        ai_move = Self::best_spot(&self.board);                        
-                    }
-                    _ => todo!(),
+                    None => height / 2 * width + width / 2,
+                });
+            }
+            Shortcut::PlaceAtCursor => {
+                if let Some(cursor) = self.cursor.filter(|_| live) {
+                    return self.button_handler(cursor);
+                }
+            }
+            Shortcut::Keypad(digit) => {
+                if live && (width, height) == (3, 3) {
+                    let index = (2 - (digit - 1) / 3) * 3 + (digit - 1) % 3;
+                    self.cursor = Some(index);
+                    return self.button_handler(index);
+                }
+            }
+            Shortcut::Restart => return self.new_game(),
+            Shortcut::Undo => {
+                if self.mode != Mode::AiVsAi && self.game.can_undo() {
+                    return self.undo();
+                }
+            }
+            Shortcut::Redo => {
+                if self.mode != Mode::AiVsAi && self.game.can_redo() {
+                    return self.redo();
+                }
+            }
+            Shortcut::NextMode => {
+                let next = Mode::ALL.iter().position(|&mode| mode == self.mode).map_or(0, |index| index + 1);
+                self.mode = Mode::ALL[next % Mode::ALL.len()];
+                return self.new_game();
+            }
+            Shortcut::NextTheme => {
+                self.theme = (self.theme + 1) % self.themes.len();
+            }
+            Shortcut::NextLevel => {
+                if self.mode != Mode::TwoPlayers {
+                    self.level = Level::ALL[(self.level.index() + 1) % Level::ALL.len()];
+                    return self.new_game();
                 }
-
-                self.make_ai_move(ai_move);
-
             }
-
-            
         }
+        Command::none()
     }
 
-    fn free_spot(board: &Board) -> usize {
-        let available_spots = board.available_moves();
-        let free_spot: Vec<_> = available_spots
-            .choose_multiple(&mut rand::thread_rng(), 1)
-            .collect();
-        *free_spot[0]        
+    fn button_handler(&mut self, index: usize) -> Command<Message> {
+        if !self.game.is_human_turn() {
+            return Command::none();
+        }
+        if self.game.play(index).is_err() {
+            self.shake();
+            return Command::none();
+        }
+        self.animate_move(index, self.game.status() == Status::Winner);
+        self.next_turn()
     }
 
-    fn play_block(board: &Board) -> Option<usize> {
-        let win_combos = [
-            [0, 1, 2],
-            [3, 4, 5],
-            [6, 7, 8],
-            [0, 3, 6],
-            [1, 4, 7],
-            [2, 5, 8],
-            [0, 4, 8],
-            [6, 4, 2],
-        ];
-    
-        let played_two = win_combos.iter().position(|combo| {
-            combo.iter().filter(|&&pos| board.cells[pos].state == CellState::Occupied(Player::Human)).count() == 2
-        });
-    
-        let result = match played_two {
-            Some(0..=8) => win_combos[played_two.unwrap()]
-                .iter()
-                .find(|&&pos| board.cells[pos].state == CellState::Empty)
-                .cloned(),
-            _ => None,
-        };
-        
-        result
+    // Draws in the mark just placed on `index`, then sweeps the winning line
+    // when the move `won`.
+    fn animate_move(&mut self, index: usize, won: bool) {
+        if !self.animate {
+            return;
+        }
+        self.now = Instant::now();
+        self.animations.placed = Some((index, self.now));
+        self.animations.won = won.then_some(self.now + PLACE_DURATION);
     }
-    
-
-    fn closest_spot(board: &Board, game: &Game) -> usize {
-        let available_spots = board.available_moves();
-        let hu_played_spots = board.hu_played_moves();
-        let play_block = Self::play_block(board);
 
-        if play_block != None {
-            return play_block.unwrap();
+    fn shake(&mut self) {
+        if self.animate {
+            self.now = Instant::now();
+            self.animations.shaken = Some(self.now);
         }
+    }
 
-        if hu_played_spots.contains(&4) && game.playing_count == 1 {
-                let free_spot: Vec<_> =  [0, 2, 6, 8].choose_multiple(&mut rand::thread_rng(), 1)
-                .collect();
-                return *free_spot[0];
+    // Stops the search for the pending AI reply, if any, and makes sure its
+    // result is ignored should it arrive anyway.
+    fn cancel_ai(&mut self) {
+        self.ai_stop.store(true, Ordering::Relaxed);
+        self.request_id += 1;
+        self.thinking = false;
+    }
 
-        }
-        if hu_played_spots.contains(&4) && game.playing_count == 3 {
-            if hu_played_spots.contains(&2) {
-                return  6;
-            } else if hu_played_spots.contains(&6){
-                return  2;
-            } else if hu_played_spots.contains(&0){
-                return  8;
-                
-            } else if hu_played_spots.contains(&8){
-                return  0;
-                
-            }
-        }
-        if !hu_played_spots.contains(&4) && game.playing_count == 1 {
-            return  4;
-        }
-        if !hu_played_spots.contains(&4) && game.playing_count == 3 {
-            if !hu_played_spots.contains(&1) {
-                return  1;
-            } else if !hu_played_spots.contains(&7) {
-                return  7;
-            } else if !hu_played_spots.contains(&3) {
-                return  3;
-            } else if !hu_played_spots.contains(&5) {
-                return  5;
-            } 
-        }
-        let free_spot: Vec<_> = available_spots
-            .choose_multiple(&mut rand::thread_rng(), 1)
-            .collect();
-        *free_spot[0]        
+    // Computes the AI reply on the executor's thread pool, so that the UI
+    // keeps responding during long searches.
+    fn request_ai_move(&mut self) -> Command<Message> {
+        self.thinking = true;
+
+        let mut game = self.game.clone();
+        self.ai_stop = game.engine_mut().new_stop_flag();
+        let delay = Duration::from_millis(self.ai_delay.into());
+        let request_id = self.request_id;
+
+        Command::perform(
+            async move {
+                let started = Instant::now();
+                let index = game.ai_move();
+                std::thread::sleep(delay.saturating_sub(started.elapsed()));
+                index
+            },
+            move |index| Message::AiMoved(request_id, index),
+        )
     }
+}
 
+// Keyboard commands, see `shortcut` for the keys.
+#[derive(Debug, Clone, Copy)]
+enum Shortcut {
+    MoveCursor(isize, isize),
+    PlaceAtCursor,
+    // Digits 1 to 9, laid out like a numeric keypad over a 3×3 board.
+    Keypad(usize),
+    Restart,
+    Undo,
+    Redo,
+    NextMode,
+    NextLevel,
+    NextTheme,
+}
 
-    fn best_spot(board: &Board) -> usize {
-        TicTacToe::minimax(board, Player::AI).index
+// Arrows or WASD move the cursor, Enter or Space place a mark there, and
+// the digits place one directly. Keys held with Ctrl, Alt or the logo key
+// are left alone.
+fn shortcut(key_code: KeyCode, modifiers: Modifiers) -> Option<Shortcut> {
+    if modifiers.control() || modifiers.alt() || modifiers.logo() {
+        return None;
     }
-    
-    fn minimax(board: &Board, player: Player) -> Move {
-        let available_spots = board.available_moves();
-    
-        if board.check_win(Player::Human) {
-            return Move { score: -10, index: 0 };
-        } else if board.check_win(Player::AI) {
-            return Move { score: 10, index: 0 };
-        } else if available_spots.is_empty() {
-            return Move { score: 0, index: 0 };
-        }
-    
-        let mut moves = Vec::new();
-    
-        for &spot in &available_spots {
-            let mut new_board = board.clone();
-            new_board.make_move(spot, player);
-    
-            let result = Self::minimax(&new_board, match player {
-                Player::AI => Player::Human,
-                Player::Human => Player::AI,
-                _ => todo!(),
-            });
-    
-            moves.push(Move { score: result.score, index: spot });
-        }
-    
-        if player == Player::AI {
-            let best_move = moves.iter().max_by_key(|&&m| m.score).unwrap();
-            *best_move
-        } else {
-            let best_move = moves.iter().min_by_key(|&&m| m.score).unwrap();
-            *best_move
-        }
+
+    let digits = [
+        [KeyCode::Key1, KeyCode::Numpad1],
+        [KeyCode::Key2, KeyCode::Numpad2],
+        [KeyCode::Key3, KeyCode::Numpad3],
+        [KeyCode::Key4, KeyCode::Numpad4],
+        [KeyCode::Key5, KeyCode::Numpad5],
+        [KeyCode::Key6, KeyCode::Numpad6],
+        [KeyCode::Key7, KeyCode::Numpad7],
+        [KeyCode::Key8, KeyCode::Numpad8],
+        [KeyCode::Key9, KeyCode::Numpad9],
+    ];
+    if let Some(digit) = digits.iter().position(|keys| keys.contains(&key_code)) {
+        return Some(Shortcut::Keypad(digit + 1));
+    }
+
+    match key_code {
+        KeyCode::Left | KeyCode::A => Some(Shortcut::MoveCursor(-1, 0)),
+        KeyCode::Right | KeyCode::D => Some(Shortcut::MoveCursor(1, 0)),
+        KeyCode::Up | KeyCode::W => Some(Shortcut::MoveCursor(0, -1)),
+        KeyCode::Down | KeyCode::S => Some(Shortcut::MoveCursor(0, 1)),
+        KeyCode::Enter | KeyCode::NumpadEnter | KeyCode::Space => Some(Shortcut::PlaceAtCursor),
+        KeyCode::R => Some(Shortcut::Restart),
+        KeyCode::U => Some(Shortcut::Undo),
+        KeyCode::Y => Some(Shortcut::Redo),
+        KeyCode::M => Some(Shortcut::NextMode),
+        KeyCode::L => Some(Shortcut::NextLevel),
+        KeyCode::T => Some(Shortcut::NextTheme),
+        _ => None,
     }
-    
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 enum Message {
     ButtonPress(usize),
     Restart,
+    Undo,
+    Redo,
+    ShowPosition(Option<usize>),
+    ResetScores,
+    GamePathChanged(String),
+    SaveGame,
+    OpenGame,
+    ReplayGame,
+    ReplayTick(u64),
+    ToggleReplay,
+    StepReplay(usize),
+    ReplayDelayChanged(u32),
+    ExitReplay,
+    ToggleFullscreen,
+    WindowResized(u32, u32),
+    WindowMoved(i32, i32),
+    CloseRequested,
+    Shortcut(Shortcut),
+    ThemeChanged(usize),
+    AnimateChanged(bool),
+    AnimationFrame(Instant),
     LevelChanged(Level),
     ModeChanged(Mode),
+    FirstMoveChanged(FirstMove),
+    VariantChanged(Variant),
+    PlayoutsChanged(u32),
+    AiDelayChanged(u32),
+    AiMoved(u64, Option<usize>),
 }
 
 impl Application for TicTacToe {
     type Executor = executor::Default;
     type Message = Message;
-    type Flags = ();
+    type Flags = (Options, WindowState);
     type Theme = Theme;
 
-    fn new(_flags: Self::Flags) -> (Self, Command<Message>) {
-        (TicTacToe::default(), window::change_mode(iced::window::Mode::Fullscreen))
+    fn new((options, window): Self::Flags) -> (Self, Command<Message>) {
+        let mut tic_tac_toe = TicTacToe {
+            mode: options.mode,
+            level: options.level,
+            first_move: options.first_move,
+            variant: options.variant,
+            animate: !options.no_animations,
+            window,
+            viewport: window.size,
+            ..TicTacToe::default()
+        };
+        if let Some(dir) = palette::themes_dir() {
+            tic_tac_toe.themes.extend(palette::load_dir(&dir));
+        }
+        if let Some(path) = scoreboard_path() {
+            match Scoreboard::load(&path) {
+                Ok(scoreboard) => tic_tac_toe.scoreboard = scoreboard,
+                Err(error) => eprintln!("could not load the scoreboard from {}: {error}", path.display()),
+            }
+        }
+        let command = tic_tac_toe.new_game();
+        if !window.fullscreen {
+            return (tic_tac_toe, command);
+        }
+        (tic_tac_toe, Command::batch([command, window::change_mode(window::Mode::Fullscreen)]))
     }
 
     fn title(&self) -> String {
         "Tic Tac Toe with Iced".into()
     }
 
+    fn theme(&self) -> Theme {
+        Theme::new(self.themes[self.theme].1)
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let events = iced::subscription::events_with(|event, status| match event {
+            Event::Window(window::Event::Resized { width, height }) => Some(Message::WindowResized(width, height)),
+            Event::Window(window::Event::Moved { x, y }) => Some(Message::WindowMoved(x, y)),
+            Event::Window(window::Event::CloseRequested) => Some(Message::CloseRequested),
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key_code: KeyCode::F11,
+                ..
+            }) => Some(Message::ToggleFullscreen),
+            // Keys typed into the file name field are not shortcuts.
+            Event::Keyboard(keyboard::Event::KeyPressed { key_code, modifiers })
+                if status == iced::event::Status::Ignored =>
+            {
+                shortcut(key_code, modifiers).map(Message::Shortcut)
+            }
+            _ => None,
+        });
+
+        // Frames are only asked for while something moves.
+        if self.animations.is_running(self.now) {
+            Subscription::batch([events, window::frames().map(Message::AnimationFrame)])
+        } else {
+            events
+        }
+    }
+
     fn update(&mut self, message: Message) -> Command<Message> {
         match message {
             Message::Restart => {
-                let mode = self.game.mode.clone();
-                let level = self.game.level.clone();
-                *self = TicTacToe::default();
-                self.game.mode = mode;
-                self.game.level = level;
-                if self.game.mode == Mode::OnePlayer {
-                    self.player = Player::Human;
+                return self.new_game();
+            }
+            Message::Undo => {
+                return self.undo();
+            }
+            Message::Redo => {
+                return self.redo();
+            }
+            Message::GamePathChanged(path) => {
+                self.game_path = path;
+            }
+            Message::SaveGame => {
+                self.save_game();
+            }
+            Message::OpenGame => {
+                return self.open_game();
+            }
+            Message::ReplayGame => {
+                return self.replay_game();
+            }
+            Message::ReplayTick(replay_id) => {
+                if replay_id == self.replay_id {
+                    return self.replay_tick();
                 }
-                self.check_for_winner();
+            }
+            Message::ToggleReplay => {
+                return self.toggle_replay();
+            }
+            Message::StepReplay(shown) => {
+                self.step_replay(shown);
+            }
+            Message::ReplayDelayChanged(replay_delay) => {
+                self.replay_delay = replay_delay;
+            }
+            Message::ToggleFullscreen => {
+                self.window.fullscreen = !self.window.fullscreen;
+                return window::change_mode(if self.window.fullscreen {
+                    window::Mode::Fullscreen
+                } else {
+                    window::Mode::Windowed
+                });
+            }
+            // Only the windowed geometry is worth remembering.
+            Message::WindowResized(width, height) => {
+                self.viewport = (width, height);
+                if !self.window.fullscreen {
+                    self.window.size = (width, height);
+                }
+            }
+            Message::WindowMoved(x, y) => {
+                if !self.window.fullscreen {
+                    self.window.position = Some((x, y));
+                }
+            }
+            Message::CloseRequested => {
+                if let Some(path) = WindowState::path() {
+                    if let Err(error) = self.window.save(&path) {
+                        eprintln!("could not save the window state to {}: {error}", path.display());
+                    }
+                }
+                return window::close();
+            }
+            Message::Shortcut(shortcut) => {
+                return self.handle_shortcut(shortcut);
+            }
+            Message::ThemeChanged(theme) => {
+                self.theme = theme;
+            }
+            Message::AnimateChanged(animate) => {
+                self.animate = animate;
+                self.animations = Animations::default();
+            }
+            Message::AnimationFrame(now) => {
+                self.now = now;
+            }
+            Message::ExitReplay => {
+                self.replay = None;
+                self.replay_id += 1;
+            }
+            Message::ResetScores => {
+                self.scoreboard = Scoreboard::default();
+                self.save_scoreboard();
+            }
+            Message::ShowPosition(ply) => {
+                self.viewing = ply.filter(|&ply| ply < self.game.playing_count());
             }
             Message::ButtonPress(index) => {
-                self.button_handler(index);
+                return self.button_handler(index);
+            }
+            Message::AiMoved(request_id, index) => {
+                if request_id != self.request_id {
+                    return Command::none();
+                }
+
+                self.thinking = false;
+                if let Some(index) = index {
+                    if self.game.play(index).is_ok() {
+                        self.animate_move(index, self.game.status() == Status::Winner);
+                    }
+                }
+                return self.next_turn();
             }
             Message::ModeChanged(mode) => {
-                *self = TicTacToe::default();
-                self.player = Player::Human;
-                self.message = format!("{} turn.", "Human".to_string());
-                self.game.mode = mode;
+                self.mode = mode;
+                return self.new_game();
             }
             Message::LevelChanged(level) => {
-                let mode = self.game.mode.clone();
-                *self = TicTacToe::default();
-                self.game.mode = mode;
-                self.player = Player::Human;
-                self.message = format!("{} turn.", "Human".to_string());
-                self.game.level = Some(level);
+                self.level = level;
+                return self.new_game();
+            }
+            Message::FirstMoveChanged(first_move) => {
+                self.first_move = first_move;
+                return self.new_game();
+            }
+            Message::VariantChanged(variant) => {
+                self.variant = variant;
+                return self.new_game();
+            }
+            Message::PlayoutsChanged(playouts) => {
+                self.playouts = playouts;
+                self.game.engine_mut().mcts.budget = Budget::Iterations(playouts);
+            }
+            Message::AiDelayChanged(ai_delay) => {
+                self.ai_delay = ai_delay;
             }
         }
         Command::none()
     }
 
-    fn view(&self) -> Element<Message> {
-        let board_button = |state: &CellState, index: usize| -> Button<'_, Message, Renderer> {
-            let bt_text = match state {
-                CellState::Occupied(Player::X) => "X",
-                CellState::Occupied(Player::O) => "O",
-                CellState::Occupied(Player::AI) => "O",
-                CellState::Occupied(Player::Human) => "X",
-                CellState::Empty => "",
-            };
-    
-            Button::new(
-                Text::new(bt_text)
-                    .horizontal_alignment(iced::alignment::Horizontal::Center)
-                    .vertical_alignment(iced::alignment::Vertical::Center)
-                    .size(TEXT_SIZE)
-                    .style(self.board.cells[index].color),
-            )
-            .width(BUTTON_SIZE)
-            .height(BUTTON_SIZE)
-            .style(self.board.cells[index].background)
-            .on_press(Message::ButtonPress(index))
-        };
-    
+    fn view(&self) -> Element<'_, Message> {
+        let text_size = self.control_text_size();
         let restart_button = Button::new(
             Text::new("Restart")
                 .horizontal_alignment(iced::alignment::Horizontal::Center)
                 .vertical_alignment(iced::alignment::Vertical::Center)
-                .size(TEXT_SIZE),
+                .size(text_size),
         )
-        .width(620)
-        .height(BUTTON_SIZE)
+        .padding([self.scaled(8), self.scaled(48)])
         .on_press(Message::Restart);
+
+        // Stepping through the history only makes sense with a human playing.
+        let history_button = |label, message, enabled: bool| -> Button<'_, Message, Renderer> {
+            let button = Button::new(
+                Text::new(label)
+                    .horizontal_alignment(iced::alignment::Horizontal::Center)
+                    .vertical_alignment(iced::alignment::Vertical::Center)
+                    .size(text_size),
+            )
+            .padding([self.scaled(8), self.scaled(24)]);
+
+            if enabled && self.mode != Mode::AiVsAi {
+                button.on_press(message)
+            } else {
+                button
+            }
+        };
+        let history: Row<'_, Message, Renderer> = row![
+            history_button("Undo", Message::Undo, self.game.can_undo()),
+            history_button("Redo", Message::Redo, self.game.can_redo()),
+        ]
+        .spacing(self.scaled(20));
     
-        let message = Text::new(&self.message)
+        let status = match &self.replay {
+            Some(replay) => format!("Replay: move {} of {}", replay.shown, replay.game.playing_count()),
+            None => self.message.clone(),
+        };
+        let message = Text::new(status)
             .horizontal_alignment(iced::alignment::Horizontal::Center)
             .vertical_alignment(iced::alignment::Vertical::Center)
-            .size(30);
-    
-        let board: Vec<Element<Message>> = self.board.cells.chunks_exact(3).enumerate().map(|(row, row_board)| {
-            let row_buttons: Vec<Element<Message>> = row_board.iter().enumerate().map(|(col, cell_state)| {
-                let button = board_button(&cell_state.state, (row * 3 + col + 1) - 1);
-                button.into() // Convert Button to Element
-            }).collect();
-    
-            Row::new()
-                .spacing(10)
-                .align_items(iced::Alignment::Center)
-                .push(Row::with_children(row_buttons)) // Wrap the buttons in another Row
-                .into() // Convert Row to Element
-        }).collect();
+            .size(text_size);
+
+        // The board shares what room the controls leave with the settings.
+        let board = self.board_view();
 
         let mode: Row<'_, Message, Renderer>  =
-            [Mode::OnePlayer, Mode::TwoPlayers]
+            Mode::ALL
                 .iter()
                 .fold(
-                    row![Text::new("Mode:")].spacing(10),
+                    row![Text::new("Mode:").size(self.scaled(20))].spacing(self.scaled(10)),
                     |mode, mode_sel| {
                         mode.push(Radio::new(
                             format!("{mode_sel:?}"),
                             *mode_sel,
-                            Some(match self.game.mode {
-                                Mode::OnePlayer => Mode::OnePlayer,
-                                Mode::TwoPlayers => Mode::TwoPlayers,
-                            }),
+                            Some(self.mode),
                             Message::ModeChanged,
-                        ))
+                        )
+                        .size(self.scaled(20))
+                        .text_size(self.scaled(20)))
                     },
                 );
 
 
 
         let level: Row<'_, Message, Renderer>  =
-            [Level::Easy, Level::Medium, Level::Hard]
+            Level::ALL
                 .iter()
                 .fold(
-                    row![Text::new("Level:")].spacing(10),
+                    row![Text::new("Level:").size(self.scaled(20))].spacing(self.scaled(10)),
                     |level, level_sel| {
                         level.push(Radio::new(
                             format!("{level_sel:?}"),
                             *level_sel,
-                            Some(match self.game.level {
-                                Some(Level::Easy) => Level::Easy,
-                                Some(Level::Medium) => Level::Medium,
-                                Some(Level::Hard) => Level::Hard,
-                                None => Level::Easy
-                            }),
+                            Some(self.level),
                             Message::LevelChanged,
-                        ))
+                        )
+                        .size(self.scaled(20))
+                        .text_size(self.scaled(20)))
+                    },
+                );
+
+        let first_move: Row<'_, Message, Renderer>  =
+            FirstMove::ALL
+                .iter()
+                .fold(
+                    row![Text::new("First move:").size(self.scaled(20))].spacing(self.scaled(10)),
+                    |first_move, first_move_sel| {
+                        first_move.push(Radio::new(
+                            first_move_sel.to_string(),
+                            *first_move_sel,
+                            Some(self.first_move),
+                            Message::FirstMoveChanged,
+                        )
+                        .size(self.scaled(20))
+                        .text_size(self.scaled(20)))
                     },
                 );
 
+        let playouts: Row<'_, Message, Renderer> = row![
+            Text::new(format!("Playouts: {}", self.playouts)).size(self.scaled(20)),
+            Slider::new(1_000..=100_000, self.playouts, Message::PlayoutsChanged)
+                .step(1_000)
+                .width(self.scaled(300)),
+        ]
+        .spacing(self.scaled(10));
+
+        let ai_delay: Row<'_, Message, Renderer> = row![
+            Text::new(format!("AI delay: {} ms", self.ai_delay)).size(self.scaled(20)),
+            Slider::new(0..=2_000, self.ai_delay, Message::AiDelayChanged)
+                .step(100)
+                .width(self.scaled(300)),
+        ]
+        .spacing(self.scaled(10));
+
+        // Too many boards for a single row.
+        let variants = Variant::ALL.chunks(4).fold(Column::new().spacing(self.scaled(10)), |variants, chunk| {
+            variants.push(chunk.iter().fold(Row::new().spacing(self.scaled(10)), |variant, variant_sel| {
+                variant.push(Radio::new(
+                    variant_sel.to_string(),
+                    *variant_sel,
+                    Some(self.variant),
+                    Message::VariantChanged,
+                )
+                .size(self.scaled(20))
+                .text_size(self.scaled(20)))
+            }))
+        });
+        let variant: Row<'_, Message, Renderer> =
+            row![Text::new("Board:").size(self.scaled(20)), variants].spacing(self.scaled(10));
+
+        let animate: Row<'_, Message, Renderer> = row![
+            Text::new("Animations:").size(self.scaled(20)),
+            Radio::new("On", true, Some(self.animate), Message::AnimateChanged)
+                .size(self.scaled(20))
+                .text_size(self.scaled(20)),
+            Radio::new("Off", false, Some(self.animate), Message::AnimateChanged)
+                .size(self.scaled(20))
+                .text_size(self.scaled(20)),
+        ]
+        .spacing(self.scaled(10));
     
+        let settings = Column::new()
+            .spacing(self.scaled(10))
+            .width(Length::Fill)
+            .align_items(iced::Alignment::Center)
+            .push(mode)
+            .push(variant)
+            .push(animate)
+            .push(if self.mode != Mode::TwoPlayers { level } else {Row::new()
+                .spacing(self.scaled(10))
+                .align_items(iced::Alignment::Center) })
+            .push(if self.mode == Mode::OnePlayer { first_move } else { Row::new() })
+            // Gomoku searches are timed instead, see `GOMOKU_TIME_LIMIT`.
+            .push(if self.mode != Mode::TwoPlayers && self.level == Level::MonteCarlo && !self.variant.is_gomoku() {
+                playouts
+            } else {
+                Row::new()
+            })
+            .push(if self.mode != Mode::TwoPlayers { ai_delay } else { Row::new() });
+
+        // The settings scroll rather than squeeze the board on small windows.
         let content = Column::new()
-            .spacing(20)
+            .spacing(self.scaled(20))
             .align_items(iced::Alignment::Center)
+            .width(Length::FillPortion(3))
+            .height(Length::Fill)
             .push(message)
-            .push(Column::with_children(board)) // Convert Vec<Element> to a single widget element
-            .push(mode)
-            .push(if self.game.mode == Mode::OnePlayer { level } else {Row::new()
-                .spacing(10)
-                .align_items(iced::Alignment::Center)
-                .into() })
+            .push(Container::new(board).width(Length::Fill).height(Length::FillPortion(3)))
+            .push(Scrollable::new(settings).height(Length::FillPortion(1)))
+            .push(history)
             .push(restart_button);
     
-        Container::new(content)
+        let fullscreen_label = if self.window.fullscreen { "Windowed (F11)" } else { "Full screen (F11)" };
+        let fullscreen_button = Button::new(Text::new(fullscreen_label).size(self.scaled(20)))
+        .padding([self.scaled(4), self.scaled(8)])
+        .on_press(Message::ToggleFullscreen);
+
+        // Scrolls as a whole when the window is too short for it, with room
+        // on the right for the scroller.
+        let side_panel = Column::new()
+            .spacing(self.scaled(40))
+            .padding([0, self.scaled(20), 0, 0])
+            .push(fullscreen_button)
+            .push(self.theme_panel())
+            .push(if self.replay.is_some() { self.replay_panel() } else { self.history_panel() })
+            .push(self.score_panel())
+            .push(self.file_panel());
+        let side_panel = Scrollable::new(side_panel).width(Length::FillPortion(1)).height(Length::Fill);
+
+        Container::new(row![content, side_panel]
+            .spacing(self.scaled(40))
+            .padding(self.scaled(20))
+            .height(Length::Fill))
             .width(Length::Fill)
             .height(Length::Fill)
             .center_x()
             .center_y()
             .into()
     }
-    
+}
+
+impl TicTacToe {
+    // Scales a size in pixels, picked for a 1200 pixel window, with the
+    // smaller side of the window. There is no upper bound, so that the
+    // controls grow on large screens, but they stop shrinking at a little over
+    // half size to stay readable.
+    fn scaled(&self, size: u16) -> u16 {
+        let (width, height) = self.viewport;
+        let side = width.min(height).max(640);
+        (u32::from(size) * side / 1200).try_into().unwrap_or(u16::MAX)
+    }
+
+    // Text size of the main controls and the panel titles.
+    fn control_text_size(&self) -> u16 {
+        self.scaled(30)
+    }
+
+    // The shown position, drawn as large as the space left allows.
+    fn board_view(&self) -> Element<'_, Message> {
+        let (game, ply) = self.shown_position();
+        let live = self.replay.is_none() && self.viewing.is_none();
+        let complete = ply == game.playing_count();
+        let game_over = complete && game.status() != Status::Playing;
+
+        let last_move = ply.checked_sub(1).map(|last| game.history()[last].index);
+        let animations = self.animations;
+
+        let board = BoardCanvas {
+            board: if complete { game.board().clone() } else { game.board_at(ply) },
+            meta: if complete { game.meta().cloned() } else { game.meta_at(ply) },
+            last_move,
+            winning_lines: if complete { game.winning_lines() } else { Vec::new() },
+            winning_cells: if complete { game.winning_cells() } else { Vec::new() },
+            game_over,
+            coordinates: game.variant().is_some_and(Variant::is_gomoku),
+            cursor: self.cursor.filter(|_| live),
+            preview: (live && !game_over && self.game.is_human_turn()).then(|| self.game.player()),
+            placing: animations
+                .placed
+                .filter(|(index, _)| Some(*index) == last_move)
+                .map(|(index, started)| (index, progress(started, PLACE_DURATION, self.now))),
+            sweep: animations.won.map_or(1.0, |started| progress(started, SWEEP_DURATION, self.now)),
+            // A few swings of a twentieth of a cell, dying out.
+            shake: animations.shaken.map_or(0.0, |started| {
+                let done = progress(started, SHAKE_DURATION, self.now);
+                (done * 3.0 * TAU).sin() * (1.0 - done) * 0.05
+            }),
+        };
+
+        Canvas::new(board).width(Length::Fill).height(Length::Fill).into()
+    }
+
+    // Game and number of its moves to show on the board: the replayed game,
+    // a past position picked in the history panel, or the live game.
+    fn shown_position(&self) -> (&Game, usize) {
+        match (&self.replay, self.viewing) {
+            (Some(replay), _) => (&replay.game, replay.shown),
+            (None, Some(ply)) => (&self.game, ply),
+            (None, None) => (&self.game, self.game.playing_count()),
+        }
+    }
+
+    // Move list next to the board. Clicking a move shows the position right
+    // after it, read-only, until returning to the live game.
+    fn history_panel(&self) -> Element<'_, Message> {
+        let history = self.game.history();
+        let board = self.game.board();
+        let shown = self.viewing.unwrap_or(history.len());
+
+        let small_button = |label: String, message: Option<Message>, selected: bool| -> Button<'_, Message, Renderer> {
+            let button = Button::new(Text::new(label).size(self.scaled(20)))
+                .padding([self.scaled(4), self.scaled(8)])
+                .style(if selected { theme::Background::Secondary } else { theme::Background::Primary });
+            match message {
+                Some(message) => button.on_press(message),
+                None => button,
+            }
+        };
+
+        let moves: Vec<Element<Message>> = history.chunks(2).enumerate().map(|(turn, pair)| {
+            pair.iter().enumerate().fold(
+                row![Text::new(format!("{}.", turn + 1)).size(self.scaled(20)).width(self.scaled(40))]
+                    .spacing(self.scaled(10)),
+                |line, (offset, played)| {
+                    let ply = 2 * turn + offset + 1;
+                    line.push(small_button(
+                        format!("{} {}", played.player, board.cell_name(played.index)),
+                        Some(Message::ShowPosition(Some(ply))),
+                        self.viewing == Some(ply),
+                    ))
+                },
+            )
+            .into()
+        }).collect();
+
+        let header = match self.viewing {
+            Some(ply) => format!("Move {ply} of {}", history.len()),
+            None => "Live".to_string(),
+        };
+
+        let navigation: Row<'_, Message, Renderer> = row![
+            small_button("<".into(), (shown > 0).then_some(Message::ShowPosition(Some(shown.saturating_sub(1)))), false),
+            small_button(">".into(), self.viewing.map(|ply| Message::ShowPosition(Some(ply + 1))), false),
+            small_button("Live".into(), self.viewing.map(|_| Message::ShowPosition(None)), false),
+        ]
+        .spacing(self.scaled(10));
 
+        // The side panel scrolls too, so the move list needs a height of its
+        // own to scroll within.
+        let moves = Scrollable::new(Column::with_children(moves).spacing(self.scaled(5))).height(self.scaled(300));
+
+        Column::new()
+            .spacing(self.scaled(20))
+            .width(Length::Fill)
+            .push(Text::new(header).size(self.control_text_size()))
+            .push(moves)
+            .push(navigation)
+            .into()
+    }
+
+    // Playback controls shown instead of the history panel during a replay.
+    fn replay_panel(&self) -> Element<'_, Message> {
+        let Some(replay) = &self.replay else {
+            return Column::new().into();
+        };
+        let total = replay.game.playing_count();
+        let control = |label, message: Option<Message>| {
+            let button = Button::new(Text::new(label).size(self.scaled(20))).padding([self.scaled(4), self.scaled(8)]);
+            match message {
+                Some(message) => button.on_press(message),
+                None => button,
+            }
+        };
+
+        let controls: Row<'_, Message, Renderer> = row![
+            control("|<", (replay.shown > 0).then_some(Message::StepReplay(0))),
+            control("<", replay.shown.checked_sub(1).map(Message::StepReplay)),
+            control(if replay.playing { "Pause" } else { "Play" }, (total > 0).then_some(Message::ToggleReplay)),
+            control(">", (replay.shown < total).then_some(Message::StepReplay(replay.shown + 1))),
+            control(">|", (replay.shown < total).then_some(Message::StepReplay(total))),
+        ]
+        .spacing(self.scaled(10));
+
+        let speed: Row<'_, Message, Renderer> = row![
+            Text::new(format!("{} ms per move", self.replay_delay)).size(self.scaled(20)),
+            Slider::new(100..=3_000, self.replay_delay, Message::ReplayDelayChanged)
+                .step(100)
+                .width(self.scaled(150)),
+        ]
+        .spacing(self.scaled(10));
+
+        let moves = notation::move_list(&replay.game);
+
+        Column::new()
+            .spacing(self.scaled(20))
+            .width(Length::Fill)
+            .push(Text::new("Replay").size(self.control_text_size()))
+            .push(Text::new(moves).size(self.scaled(20)))
+            .push(controls)
+            .push(speed)
+            .push(control("Exit replay", Some(Message::ExitReplay)))
+            .into()
+    }
+
+    fn file_panel(&self) -> Element<'_, Message> {
+        let action = |label| {
+            Button::new(Text::new(label).size(self.scaled(20))).padding([self.scaled(4), self.scaled(8)])
+        };
+
+        Column::new()
+            .spacing(self.scaled(10))
+            .width(Length::Fill)
+            .push(Text::new("Game file").size(self.control_text_size()))
+            .push(
+                TextInput::new("path of the game file", &self.game_path)
+                    .on_input(Message::GamePathChanged)
+                    .size(self.scaled(20)),
+            )
+            .push(row![
+                action("Save game").on_press(Message::SaveGame),
+                action("Open game").on_press(Message::OpenGame),
+                action("Replay game").on_press(Message::ReplayGame),
+            ].spacing(self.scaled(10)))
+            .push(Text::new(&self.file_status).size(self.scaled(20)))
+            .into()
+    }
+
+    fn theme_panel(&self) -> Element<'_, Message> {
+        self.themes.iter().enumerate().fold(
+            Column::new().spacing(self.scaled(10)).push(Text::new("Theme").size(self.control_text_size())),
+            |themes, (index, (name, _))| {
+                themes.push(
+                    Radio::new(name.clone(), index, Some(self.theme), Message::ThemeChanged)
+                        .size(self.scaled(20))
+                        .text_size(self.scaled(20)),
+                )
+            },
+        )
+        .into()
+    }
+
+    fn score_panel(&self) -> Element<'_, Message> {
+        let tally_text = |name: String, tally: Tally, labels: [&str; 3]| {
+            Text::new(format!(
+                "{name}: {} {} · {} {} · {} {}",
+                labels[0], tally.wins, labels[1], tally.losses, labels[2], tally.draws
+            ))
+            .size(self.scaled(20))
+        };
+
+        let scores = Level::ALL.iter().fold(
+            Column::new()
+                .spacing(self.scaled(5))
+                .push(tally_text("Two players".into(), self.scoreboard.two_players, ["X", "O", "draws"])),
+            |scores, level| {
+                scores.push(tally_text(
+                    format!("{level:?}"),
+                    self.scoreboard.against(*level),
+                    ["won", "lost", "drawn"],
+                ))
+            },
+        );
+
+        Column::new()
+            .spacing(self.scaled(10))
+            .push(Text::new("Scores").size(self.control_text_size()))
+            .push(scores)
+            .push(
+                Button::new(Text::new("Reset scores").size(self.scaled(20)))
+                    .padding([self.scaled(4), self.scaled(8)])
+                    .on_press(Message::ResetScores),
+            )
+            .into()
+    }
 }
 
 mod widget {
@@ -603,22 +1254,38 @@ mod widget {
     pub type Column<'a, Message, Renderer> = iced::widget::Column<'a, Message, Renderer>;
     pub type Row<'a, Message, Renderer> = iced::widget::Row<'a, Message, Renderer>;
     pub type Radio<'a, Message, Renderer> = iced::widget::Radio<Message, Renderer>;
+    pub type Scrollable<'a, Message, Renderer> = iced::widget::Scrollable<'a, Message, Renderer>;
+    pub type TextInput<'a, Message, Renderer> = iced::widget::TextInput<'a, Message, Renderer>;
+    pub type Slider<'a, T, Message, Renderer> = iced::widget::Slider<'a, T, Message, Renderer>;
 }
 
 mod theme {
-    use iced::widget::{button, container, text, radio};
-    use iced::{application, color, Background as Theme_Background, BorderRadius, Color as Theme_Color};
+    use iced::widget::{button, container, text, text_input, radio, scrollable, slider};
+    use iced::{application, Background as Theme_Background, BorderRadius, Color as Theme_Color};
+    use crate::palette::Palette;
 
     #[derive(Debug, Clone, Copy, Default)]
-    pub struct Theme;
+    pub struct Theme {
+        palette: Palette,
+    }
+
+    impl Theme {
+        pub fn new(palette: Palette) -> Self {
+            Theme { palette }
+        }
+
+        pub fn palette(&self) -> Palette {
+            self.palette
+        }
+    }
 
     impl application::StyleSheet for Theme {
         type Style = ();
 
         fn appearance(&self, _style: &Self::Style) -> application::Appearance {
             application::Appearance {
-                background_color: color!(0x28, 0x28, 0x28),
-                text_color: color!(0xeb, 0xdb, 0xb2),
+                background_color: self.palette.background,
+                text_color: self.palette.text,
             }
         }
     }
@@ -627,12 +1294,6 @@ mod theme {
     pub enum Color {
         #[default]
         Primary,
-        PlayerX,
-        PlayerO,
-        Human,
-        AI,
-        #[allow(dead_code)]
-        Winner
     }
 
     impl text::StyleSheet for Theme {
@@ -640,12 +1301,7 @@ mod theme {
 
         fn appearance(&self, style: Self::Style) -> text::Appearance {
             match style {
-                Color::Primary => text::Appearance {color: color!(0xeb, 0xdb, 0xb2).into()},
-                Color::PlayerX => text::Appearance {color: Some(Theme_Color::from_rgb(250.0, 0.0, 0.0))},
-                Color::PlayerO => text::Appearance {color: Some(Theme_Color::from_rgb(0.0, 51.0, 0.0))},
-                Color::Human => text::Appearance {color: Some(Theme_Color::from_rgb(250.0, 0.0, 0.0))},
-                Color::AI => text::Appearance {color: Some(Theme_Color::from_rgb(0.0, 51.0, 0.0))},
-                Color::Winner => text::Appearance {color: color!(0xeb, 0xdb, 0xb2).into()},            
+                Color::Primary => text::Appearance {color: Some(self.palette.text)},
             }
         }
     }
@@ -665,7 +1321,7 @@ mod theme {
             match style {
                 Container::Default => container::Appearance::default(),
                 Container::Bordered => container::Appearance {
-                    border_color: color!(0x45, 0x85, 0x88),
+                    border_color: self.palette.accent,
                     border_width: 1.0,
                     border_radius: BorderRadius::from(4.0),
                     ..Default::default()
@@ -678,7 +1334,6 @@ mod theme {
     pub enum Background {
         #[default]
         Primary,
-        #[allow(dead_code)]
         Secondary,
     }
 
@@ -689,32 +1344,47 @@ mod theme {
                 Background::Primary => button::Appearance {
                     border_radius: BorderRadius::from(4.0),
                     border_width: 1.0,
-                    border_color: color!(0x45, 0x85, 0x88),
+                    border_color: self.palette.accent,
+                    text_color: self.palette.text,
                     ..Default::default()
                 },
                 Background::Secondary => button::Appearance {
                     border_radius: BorderRadius::from(4.0),
                     border_width: 1.0,
-                    border_color: color!(0x45, 0x85, 0x88),
-                    background: Some(Theme_Background::from(Theme_Color::from_rgb(60.0, 56.0, 54.0))),
+                    border_color: self.palette.accent,
+                    background: Some(Theme_Background::from(self.palette.surface)),
+                    text_color: self.palette.text,
                     ..Default::default()
                 },
             }
         }
+        fn hovered(&self, style: &Self::Style) -> button::Appearance {
+            let active = self.active(style);
+            let background = match style {
+                Background::Secondary => self.palette.hovered,
+                _ => self.palette.surface,
+            };
+            button::Appearance {
+                background: Some(Theme_Background::from(background)),
+                ..active
+            }
+        }
         fn pressed(&self, style: &Self::Style) -> button::Appearance {
             match style {
                 Background::Primary => button::Appearance {
-                    background: Some(Theme_Background::from(Theme_Color::from_rgb(255.0, 255.0, 255.0))),
+                    background: Some(Theme_Background::from(self.palette.hovered)),
                     border_radius: BorderRadius::from(4.0),
                     border_width: 1.0,
-                    border_color: color!(0x45, 0x85, 0x88),
+                    border_color: self.palette.accent,
+                    text_color: self.palette.text,
                     ..Default::default()
                 },
                 Background::Secondary => button::Appearance {
-                    background: Some(Theme_Background::from(Theme_Color::from_rgb(60.0, 56.0, 54.0))),
+                    background: Some(Theme_Background::from(self.palette.surface)),
                     border_radius: BorderRadius::from(4.0),
                     border_width: 1.0,
-                    border_color: color!(0x45, 0x85, 0x88),
+                    border_color: self.palette.accent,
+                    text_color: self.palette.text,
                     ..Default::default()
                 },
             }
@@ -735,9 +1405,9 @@ mod theme {
         fn active(&self, _style: &Self::Style, _is_selected: bool) -> radio::Appearance {
             radio::Appearance { 
                 background: iced::Color::TRANSPARENT.into(),
-                dot_color: color!(0xeb, 0xdb, 0xb2).into(),
+                dot_color: self.palette.text,
                 border_width: 1.0,
-                border_color: color!(0xeb, 0xdb, 0xb2).into(),
+                border_color: self.palette.text,
                 text_color: None,                
              }
         }
@@ -745,9 +1415,9 @@ mod theme {
         fn hovered(&self, _style: &Self::Style, _is_selected: bool) -> radio::Appearance {
             radio::Appearance { 
                 background: iced::Color::TRANSPARENT.into(),
-                dot_color: color!(0xeb, 0xdb, 0xb2).into(),
+                dot_color: self.palette.text,
                 border_width: 1.0,
-                border_color: color!(0xeb, 0xdb, 0xb2).into(),
+                border_color: self.palette.text,
                 text_color: None,                
              }
         }
@@ -756,4 +1426,96 @@ mod theme {
 
     }
 
+    impl scrollable::StyleSheet for Theme {
+        type Style = ();
+
+        fn active(&self, _style: &Self::Style) -> scrollable::Scrollbar {
+            scrollable::Scrollbar {
+                background: None,
+                border_radius: BorderRadius::from(2.0),
+                border_width: 0.0,
+                border_color: iced::Color::TRANSPARENT,
+                scroller: scrollable::Scroller {
+                    color: self.palette.accent,
+                    border_radius: BorderRadius::from(2.0),
+                    border_width: 0.0,
+                    border_color: iced::Color::TRANSPARENT,
+                },
+            }
+        }
+
+        fn hovered(&self, style: &Self::Style, _is_mouse_over_scrollbar: bool) -> scrollable::Scrollbar {
+            self.active(style)
+        }
+    }
+
+    impl text_input::StyleSheet for Theme {
+        type Style = ();
+
+        fn active(&self, _style: &Self::Style) -> text_input::Appearance {
+            text_input::Appearance {
+                background: iced::Color::TRANSPARENT.into(),
+                border_radius: BorderRadius::from(4.0),
+                border_width: 1.0,
+                border_color: self.palette.accent,
+                icon_color: self.palette.text,
+            }
+        }
+
+        fn focused(&self, style: &Self::Style) -> text_input::Appearance {
+            text_input::Appearance {
+                border_color: self.palette.text,
+                ..self.active(style)
+            }
+        }
+
+        fn disabled(&self, style: &Self::Style) -> text_input::Appearance {
+            self.active(style)
+        }
+
+        fn placeholder_color(&self, _style: &Self::Style) -> Theme_Color {
+            self.palette.dimmed
+        }
+
+        fn value_color(&self, _style: &Self::Style) -> Theme_Color {
+            self.palette.text
+        }
+
+        fn disabled_color(&self, _style: &Self::Style) -> Theme_Color {
+            self.palette.dimmed
+        }
+
+        fn selection_color(&self, _style: &Self::Style) -> Theme_Color {
+            self.palette.accent
+        }
+    }
+
+    impl slider::StyleSheet for Theme {
+        type Style = ();
+
+        fn active(&self, _style: &Self::Style) -> slider::Appearance {
+            slider::Appearance {
+                rail: slider::Rail {
+                    colors: (self.palette.text, self.palette.accent),
+                    width: 4.0,
+                    border_radius: BorderRadius::from(2.0),
+                },
+                handle: slider::Handle {
+                    shape: slider::HandleShape::Circle { radius: 8.0 },
+                    color: self.palette.text,
+                    border_width: 1.0,
+                    border_color: self.palette.accent,
+                },
+            }
+        }
+
+        fn hovered(&self, style: &Self::Style) -> slider::Appearance {
+            self.active(style)
+        }
+
+        fn dragging(&self, style: &Self::Style) -> slider::Appearance {
+            self.active(style)
+        }
+    }
+
 }
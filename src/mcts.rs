@@ -0,0 +1,204 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::board::{Board, Player};
+
+pub const DEFAULT_ITERATIONS: u32 = 20_000;
+
+/// How many iterations run between two checks of the clock.
+const TIME_CHECK_INTERVAL: u32 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Budget {
+    Iterations(u32),
+    Time(Duration),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Config {
+    pub budget: Budget,
+    /// UCT exploration constant: higher values try less visited moves more.
+    pub exploration: f64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            budget: Budget::Iterations(DEFAULT_ITERATIONS),
+            exploration: std::f64::consts::SQRT_2,
+        }
+    }
+}
+
+/// A game the search can play: the standard board, or any position whose
+/// legal moves depend on more than the free cells.
+pub trait Position: Clone {
+    fn available_moves(&self) -> Vec<usize>;
+
+    /// Plays `index` for `player` and returns whether that won the game.
+    fn play(&mut self, index: usize, player: Player) -> bool;
+}
+
+impl Position for Board {
+    fn available_moves(&self) -> Vec<usize> {
+        Board::available_moves(self)
+    }
+
+    fn play(&mut self, index: usize, player: Player) -> bool {
+        self.make_move(index, player);
+        self.is_winning_move(index, player)
+    }
+}
+
+struct Node {
+    parent: Option<usize>,
+    spot: Option<usize>,
+    /// The player who made the move leading to this node.
+    player: Player,
+    children: Vec<usize>,
+    untried: Vec<usize>,
+    visits: u32,
+    /// Sum of the playout results from the point of view of `player`:
+    /// 1 for a win, 0.5 for a draw.
+    reward: f64,
+    terminal: bool,
+    /// Whether the move leading to this node won the game.
+    won: bool,
+}
+
+impl Node {
+    fn uct(&self, parent_visits: u32, exploration: f64) -> f64 {
+        let visits = self.visits as f64;
+        self.reward / visits + exploration * ((parent_visits as f64).ln() / visits).sqrt()
+    }
+}
+
+/// Monte Carlo tree search with UCT selection and random playouts. Returns
+/// the most visited move for `player` once the budget is spent.
+pub fn search<P: Position>(position: &P, player: Player, config: &Config) -> Option<usize> {
+    search_until(position, player, config, &AtomicBool::new(false))
+}
+
+/// Same as `search`, also ending early once `stop` is raised from another
+/// thread.
+pub fn search_until<P: Position>(position: &P, player: Player, config: &Config, stop: &AtomicBool) -> Option<usize> {
+    let root_moves = position.available_moves();
+    if root_moves.is_empty() {
+        return None;
+    }
+
+    let mut tree = vec![Node {
+        parent: None,
+        spot: None,
+        player: player.opponent(),
+        children: Vec::new(),
+        untried: root_moves,
+        visits: 0,
+        reward: 0.0,
+        terminal: false,
+        won: false,
+    }];
+
+    let mut rng = rand::thread_rng();
+    let started = Instant::now();
+    let mut iterations = 0;
+
+    while !budget_spent(config.budget, iterations, started) && !stop.load(Ordering::Relaxed) {
+        iterations += 1;
+
+        let mut scratch = position.clone();
+        let mut node = 0;
+
+        // Selection
+        while tree[node].untried.is_empty() && !tree[node].terminal {
+            let parent_visits = tree[node].visits;
+            node = *tree[node]
+                .children
+                .iter()
+                .max_by(|&&a, &&b| {
+                    tree[a]
+                        .uct(parent_visits, config.exploration)
+                        .total_cmp(&tree[b].uct(parent_visits, config.exploration))
+                })
+                .expect("a non terminal node has children once fully expanded");
+            scratch.play(tree[node].spot.unwrap(), tree[node].player);
+        }
+
+        // Expansion
+        if !tree[node].untried.is_empty() {
+            let choice = rng.gen_range(0..tree[node].untried.len());
+            let spot = tree[node].untried.swap_remove(choice);
+            let mover = tree[node].player.opponent();
+            let won = scratch.play(spot, mover);
+            let untried = if won { Vec::new() } else { scratch.available_moves() };
+
+            let child = Node {
+                parent: Some(node),
+                spot: Some(spot),
+                player: mover,
+                children: Vec::new(),
+                terminal: untried.is_empty(),
+                untried,
+                visits: 0,
+                reward: 0.0,
+                won,
+            };
+            tree.push(child);
+            let child = tree.len() - 1;
+            tree[node].children.push(child);
+            node = child;
+        }
+
+        // Simulation
+        let winner = playout(&mut scratch, &tree[node], &mut rng);
+
+        // Backpropagation
+        let mut current = Some(node);
+        while let Some(index) = current {
+            let visited = &mut tree[index];
+            visited.visits += 1;
+            visited.reward += match winner {
+                Some(winner) if winner == visited.player => 1.0,
+                Some(_) => 0.0,
+                None => 0.5,
+            };
+            current = visited.parent;
+        }
+    }
+
+    tree[0]
+        .children
+        .iter()
+        .max_by_key(|&&child| tree[child].visits)
+        .and_then(|&child| tree[child].spot)
+}
+
+fn budget_spent(budget: Budget, iterations: u32, started: Instant) -> bool {
+    match budget {
+        Budget::Iterations(limit) => iterations >= limit.max(1),
+        // Always run at least one batch so that there is a move to return.
+        Budget::Time(limit) => {
+            iterations > 0 && iterations.is_multiple_of(TIME_CHECK_INTERVAL) && started.elapsed() >= limit
+        }
+    }
+}
+
+/// Plays random moves from the position of `node` until the game ends and
+/// returns the winner, if any.
+fn playout<P: Position>(position: &mut P, node: &Node, rng: &mut impl Rng) -> Option<Player> {
+    if node.won {
+        return Some(node.player);
+    }
+
+    let mut player = node.player;
+    loop {
+        let spot = *position.available_moves().choose(rng)?;
+        player = player.opponent();
+        if position.play(spot, player) {
+            return Some(player);
+        }
+    }
+}
@@ -0,0 +1,368 @@
+//! Text notation for whole games, loosely modelled on chess PGN: a header of
+//! `[Name "value"]` tags followed by the numbered move list.
+//!
+//! ```text
+//! [Board "3x3/3"]
+//! [Mode "OnePlayer"]
+//! [Level "Hard"]
+//! [X "Human"]
+//! [O "AI (Hard)"]
+//! [Started "2026-10-18T14:03:12Z"]
+//! [Ended "2026-10-18T14:04:40Z"]
+//! [Result "Draw"]
+//!
+//! 1. b2 a1 2. c3 a3 3. a2 c2 4. b1 b3 5. c1
+//! ```
+//!
+//! `Board` gives the width, height and win length, with the number of
+//! layers after the height on stacked boards such as `4x4x4/4`. `Variant`
+//! is only written, as `[Variant "Ultimate"]` or `[Variant "StandardGomoku"]`,
+//! for games whose rules go beyond the board: Ultimate games, whose cells are
+//! named on the whole 9x9 board, and Gomoku where only exactly five in a row
+//! wins. `X` and `O` say who played each side and decide the controllers
+//! when reading; `Mode` and `Level` are only written for readers. Cells are
+//! named by `Board::cell_name`. `Result` is `X`, `O`, `Draw`, or `*` for an
+//! unfinished game, and has to match the outcome of the moves. Unknown tags
+//! are ignored.
+
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::board::{Board, Player};
+use crate::game::{Controller, Game, Mode, MoveError, Status, Variant};
+
+/// Cell names use one letter per column, and one per layer.
+const MAX_SIZE: usize = 26;
+
+/// Variants whose rules are not given by their board alone, named by the
+/// `Variant` tag.
+const TAGGED_VARIANTS: [Variant; 2] = [Variant::Ultimate, Variant::StandardGomoku];
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum NotationError {
+    MalformedTag(String),
+    MissingTag(&'static str),
+    InvalidTag(&'static str, String),
+    InvalidMove(String),
+    IllegalMove(usize, MoveError),
+    ResultMismatch,
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotationError::MalformedTag(line) => write!(f, "malformed tag {line:?}"),
+            NotationError::MissingTag(name) => write!(f, "missing {name} tag"),
+            NotationError::InvalidTag(name, value) => write!(f, "invalid {name} tag {value:?}"),
+            NotationError::InvalidMove(token) => write!(f, "invalid move {token:?}"),
+            NotationError::IllegalMove(ply, error) => write!(f, "illegal move {ply}: {error}"),
+            NotationError::ResultMismatch => write!(f, "the moves do not lead to the recorded result"),
+        }
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+pub fn write(game: &Game) -> String {
+    let board = game.board();
+    let controllers = [game.controller(Player::X), game.controller(Player::O)];
+    let mut tags = vec![(
+        "Board",
+        match board.layers() {
+            1 => format!("{}x{}/{}", board.width(), board.height(), board.win_length()),
+            layers => format!("{}x{}x{layers}/{}", board.width(), board.height(), board.win_length()),
+        },
+    )];
+    if let Some(variant) = game.variant().filter(|variant| TAGGED_VARIANTS.contains(variant)) {
+        tags.push(("Variant", format!("{variant:?}")));
+    }
+
+    if let Some((mode, level)) = Mode::of(controllers) {
+        tags.push(("Mode", format!("{mode:?}")));
+        if let Some(level) = level {
+            tags.push(("Level", format!("{level:?}")));
+        }
+    }
+    tags.push(("X", controllers[0].to_string()));
+    tags.push(("O", controllers[1].to_string()));
+    tags.push(("Started", format_time(game.started())));
+    if let Some(ended) = game.ended() {
+        tags.push(("Ended", format_time(ended)));
+    }
+    tags.push(("Result", result(game)));
+
+    let mut text: String = tags
+        .iter()
+        .map(|(name, value)| format!("[{name} \"{value}\"]\n"))
+        .collect();
+    text.push('\n');
+
+    text.push_str(&move_list(game));
+    text.push('\n');
+    text
+}
+
+/// The moves of `game` as they appear after the tags, e.g. "1. b2 a1 2. c3".
+pub fn move_list(game: &Game) -> String {
+    let moves: Vec<String> = game
+        .history()
+        .chunks(2)
+        .enumerate()
+        .map(|(turn, pair)| {
+            let cells: Vec<String> = pair.iter().map(|played| game.board().cell_name(played.index)).collect();
+            format!("{}. {}", turn + 1, cells.join(" "))
+        })
+        .collect();
+    moves.join(" ")
+}
+
+/// Rebuilds a game by replaying its moves, so that a file can only describe
+/// a legal game.
+pub fn read(text: &str) -> Result<Game, NotationError> {
+    let mut tags = Vec::new();
+    let mut tokens = Vec::new();
+
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if line.starts_with('[') {
+            tags.push(parse_tag(line)?);
+        } else {
+            tokens.extend(line.split_whitespace());
+        }
+    }
+
+    let tag = |name: &'static str| tags.iter().find(|(tag, _)| *tag == name).map(|(_, value)| *value);
+    let required = |name: &'static str| tag(name).ok_or(NotationError::MissingTag(name));
+    let controller = |name: &'static str| {
+        let value = required(name)?;
+        value.parse::<Controller>().map_err(|_| NotationError::InvalidTag(name, value.to_string()))
+    };
+    let time = |name: &'static str| {
+        tag(name)
+            .map(|value| parse_time(value).ok_or_else(|| NotationError::InvalidTag(name, value.to_string())))
+            .transpose()
+    };
+
+    let board = parse_board(required("Board")?)?;
+    let shape = |board: &Board| (board.width(), board.height(), board.layers(), board.win_length());
+    let mut game = match tag("Variant") {
+        None => Game::with_board(&board, controller("X")?, controller("O")?),
+        Some(value) => {
+            let variant = TAGGED_VARIANTS
+                .into_iter()
+                .find(|variant| format!("{variant:?}") == value && shape(&variant.board()) == shape(&board))
+                .ok_or_else(|| NotationError::InvalidTag("Variant", value.to_string()))?;
+            Game::with_variant(variant, controller("X")?, controller("O")?)
+        }
+    };
+
+    // Move numbers such as "3." are only there for readers.
+    for (ply, token) in tokens.into_iter().filter(|token| !token.ends_with('.')).enumerate() {
+        let index = board
+            .parse_cell(token)
+            .ok_or_else(|| NotationError::InvalidMove(token.to_string()))?;
+        game.play(index).map_err(|error| NotationError::IllegalMove(ply + 1, error))?;
+    }
+
+    if tag("Result").is_some_and(|recorded| recorded != result(&game)) {
+        return Err(NotationError::ResultMismatch);
+    }
+
+    let started = time("Started")?.unwrap_or_else(SystemTime::now);
+    let ended = time("Ended")?.or(game.ended());
+    game.set_times(started, ended);
+    Ok(game)
+}
+
+fn result(game: &Game) -> String {
+    match (game.status(), game.winner()) {
+        (Status::Winner, Some(winner)) => winner.to_string(),
+        (Status::Draw, _) => "Draw".to_string(),
+        _ => "*".to_string(),
+    }
+}
+
+fn parse_tag(line: &str) -> Result<(&str, &str), NotationError> {
+    let malformed = || NotationError::MalformedTag(line.to_string());
+    let inner = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')).ok_or_else(malformed)?;
+    let (name, value) = inner.split_once(' ').ok_or_else(malformed)?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .ok_or_else(malformed)?;
+    Ok((name, value))
+}
+
+fn parse_board(value: &str) -> Result<Board, NotationError> {
+    let invalid = || NotationError::InvalidTag("Board", value.to_string());
+    let (size, win_length) = value.split_once('/').ok_or_else(invalid)?;
+    let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+    let (height, layers) = height.split_once('x').unwrap_or((height, "1"));
+    let [width, height, layers, win_length]: [usize; 4] = [width, height, layers, win_length]
+        .map(|number| number.parse().unwrap_or(0));
+
+    if [width, height, layers].iter().any(|size| !(1..=MAX_SIZE).contains(size)) {
+        return Err(invalid());
+    }
+    if win_length == 0 || win_length > width.max(height).max(layers) {
+        return Err(invalid());
+    }
+    Ok(Board::with_layers(width, height, layers, win_length))
+}
+
+/// UTC date and time in RFC 3339 form, to the second.
+fn format_time(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    let time_of_day = seconds % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        time_of_day / 3_600,
+        time_of_day / 60 % 60,
+        time_of_day % 60
+    )
+}
+
+fn parse_time(value: &str) -> Option<SystemTime> {
+    let (date, time) = value.strip_suffix('Z')?.split_once('T')?;
+    let date: Vec<u64> = date.split('-').map(str::parse).collect::<Result<_, _>>().ok()?;
+    let time: Vec<u64> = time.split(':').map(str::parse).collect::<Result<_, _>>().ok()?;
+    let (&[year, month, day], &[hours, minutes, seconds]) = (&date[..], &time[..]) else {
+        return None;
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+
+    let days = u64::try_from(days_from_civil(year as i64, month, day)).ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(days * 86_400 + hours * 3_600 + minutes * 60 + seconds))
+}
+
+/// Days since 1970-01-01 in the proleptic Gregorian calendar, after Howard
+/// Hinnant's `days_from_civil`.
+fn days_from_civil(year: i64, month: u64, day: u64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u64, u64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u64;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 } as u64;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::Level;
+
+    fn play(game: &mut Game, cells: &[&str]) {
+        for cell in cells {
+            let index = game.board().parse_cell(cell).unwrap();
+            game.play(index).unwrap();
+        }
+    }
+
+    fn assert_round_trip(game: &Game) -> String {
+        let text = write(game);
+        let read_back = read(&text).unwrap();
+        assert_eq!(read_back.history(), game.history());
+        assert_eq!(read_back.status(), game.status());
+        assert_eq!(read_back.variant(), game.variant());
+        assert_eq!(write(&read_back), text);
+        text
+    }
+
+    #[test]
+    fn classic_game_round_trips() {
+        let mut game = Game::new(Controller::Human, Controller::Ai(Level::Hard));
+        play(&mut game, &["b2", "a1", "c3", "a3", "a2", "c2", "b1", "b3", "c1"]);
+        assert_eq!(game.status(), Status::Draw);
+
+        let text = assert_round_trip(&game);
+        assert!(text.contains("[Board \"3x3/3\"]"));
+        assert!(text.contains("[Result \"Draw\"]"));
+        assert!(text.ends_with("1. b2 a1 2. c3 a3 3. a2 c2 4. b1 b3 5. c1\n"));
+    }
+
+    #[test]
+    fn qubic_game_round_trips() {
+        let mut game = Game::with_variant(Variant::Qubic, Controller::Human, Controller::Human);
+        play(&mut game, &["Aa1", "Aa2", "Bb2", "Ab2", "Cc3", "Ac2", "Dd4"]);
+        assert_eq!(game.winner(), Some(Player::X));
+
+        let text = assert_round_trip(&game);
+        assert!(text.contains("[Board \"4x4x4/4\"]"));
+        assert!(!text.contains("[Variant"));
+        assert!(text.ends_with("1. Aa1 Aa2 2. Bb2 Ab2 3. Cc3 Ac2 4. Dd4\n"));
+    }
+
+    #[test]
+    fn ultimate_game_round_trips() {
+        let mut game = Game::with_variant(Variant::Ultimate, Controller::Human, Controller::Human);
+        for _ in 0..12 {
+            let index = game.available_moves()[0];
+            game.play(index).unwrap();
+        }
+
+        let text = assert_round_trip(&game);
+        assert!(text.contains("[Variant \"Ultimate\"]"));
+        assert!(text.contains("[Result \"*\"]"));
+    }
+
+    #[test]
+    fn result_has_to_match_the_moves() {
+        let mut game = Game::new(Controller::Human, Controller::Human);
+        play(&mut game, &["a1", "b1", "a2", "b2", "a3"]);
+        let text = write(&game);
+        assert!(text.contains("[Result \"X\"]"));
+
+        let forged = text.replace("[Result \"X\"]", "[Result \"O\"]");
+        assert_eq!(read(&forged).err(), Some(NotationError::ResultMismatch));
+        let unfinished = text.replace(" 3. a3", "");
+        assert_eq!(read(&unfinished).err(), Some(NotationError::ResultMismatch));
+    }
+
+    #[test]
+    fn days_around_leap_years() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+
+        // 2000 and 2024 are leap years, 1900 and 2100 are not.
+        for (year, february) in [(1900, 28), (2000, 29), (2024, 29), (2100, 28)] {
+            let last = days_from_civil(year, 2, february);
+            assert_eq!(civil_from_days(last), (year, 2, february));
+            assert_eq!(civil_from_days(last + 1), (year, 3, 1));
+            assert_eq!(days_from_civil(year, 3, 1), last + 1);
+        }
+    }
+
+    #[test]
+    fn days_round_trip() {
+        for days in days_from_civil(1896, 1, 1)..days_from_civil(2104, 12, 31) {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn times_round_trip() {
+        let time = parse_time("2024-02-29T23:59:59Z").unwrap();
+        assert_eq!(format_time(time), "2024-02-29T23:59:59Z");
+        assert_eq!(format_time(time + Duration::from_secs(1)), "2024-03-01T00:00:00Z");
+        assert_eq!(parse_time("2024-13-01T00:00:00Z"), None);
+    }
+}
@@ -0,0 +1,202 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use iced::Color;
+
+// Colours the whole interface is drawn with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    pub background: Color,
+    // Selected and highlighted buttons.
+    pub surface: Color,
+    pub hovered: Color,
+    pub text: Color,
+    // Borders, scrollbars and sliders.
+    pub accent: Color,
+    // The winning line and the last move.
+    pub highlight: Color,
+    pub dimmed: Color,
+    pub player_x: Color,
+    pub player_o: Color,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::DARK
+    }
+}
+
+const fn rgb(hex: u32) -> Color {
+    Color::from_rgb(
+        ((hex >> 16) & 0xff) as f32 / 255.0,
+        ((hex >> 8) & 0xff) as f32 / 255.0,
+        (hex & 0xff) as f32 / 255.0,
+    )
+}
+
+impl Palette {
+    pub const DARK: Palette = Palette {
+        background: rgb(0x282828),
+        surface: rgb(0x3c3836),
+        hovered: rgb(0x504945),
+        text: rgb(0xebdbb2),
+        accent: rgb(0x458588),
+        highlight: rgb(0xfabd2f),
+        dimmed: rgb(0x665c54),
+        player_x: rgb(0xff0000),
+        player_o: rgb(0x00ff00),
+    };
+
+    pub const LIGHT: Palette = Palette {
+        background: rgb(0xfbf1c7),
+        surface: rgb(0xebdbb2),
+        hovered: rgb(0xd5c4a1),
+        text: rgb(0x3c3836),
+        accent: rgb(0x076678),
+        highlight: rgb(0xb57614),
+        dimmed: rgb(0xa89984),
+        player_x: rgb(0x9d0006),
+        player_o: rgb(0x79740e),
+    };
+
+    pub const HIGH_CONTRAST: Palette = Palette {
+        background: rgb(0x000000),
+        surface: rgb(0x303030),
+        hovered: rgb(0x505050),
+        text: rgb(0xffffff),
+        accent: rgb(0xffffff),
+        highlight: rgb(0xffff00),
+        dimmed: rgb(0x808080),
+        player_x: rgb(0x00ffff),
+        player_o: rgb(0xff00ff),
+    };
+
+    // The marks use the orange and blue of the Okabe-Ito palette, which stay
+    // apart for the common forms of colour blindness.
+    pub const COLORBLIND: Palette = Palette {
+        background: rgb(0x282828),
+        surface: rgb(0x3c3836),
+        hovered: rgb(0x504945),
+        text: rgb(0xebdbb2),
+        accent: rgb(0x0072b2),
+        highlight: rgb(0xf0e442),
+        dimmed: rgb(0x665c54),
+        player_x: rgb(0xe69f00),
+        player_o: rgb(0x56b4e9),
+    };
+
+    pub const BUILT_IN: [(&'static str, Palette); 4] = [
+        ("Dark", Palette::DARK),
+        ("Light", Palette::LIGHT),
+        ("High contrast", Palette::HIGH_CONTRAST),
+        ("Colorblind", Palette::COLORBLIND),
+    ];
+
+    fn color_mut(&mut self, key: &str) -> Option<&mut Color> {
+        match key {
+            "background" => Some(&mut self.background),
+            "surface" => Some(&mut self.surface),
+            "hovered" => Some(&mut self.hovered),
+            "text" => Some(&mut self.text),
+            "accent" => Some(&mut self.accent),
+            "highlight" => Some(&mut self.highlight),
+            "dimmed" => Some(&mut self.dimmed),
+            "player_x" => Some(&mut self.player_x),
+            "player_o" => Some(&mut self.player_o),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParsePaletteError(String);
+
+impl fmt::Display for ParsePaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid theme line: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParsePaletteError {}
+
+// Where custom themes are looked for.
+pub fn themes_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("tic-tac-toe-iced").join("themes"))
+}
+
+// Reads every `.toml` file of `dir` as a theme, sorted by name. A missing
+// directory has no themes; files that cannot be read are reported and
+// skipped.
+pub fn load_dir(dir: &Path) -> Vec<(String, Palette)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut themes: Vec<(String, Palette)> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "toml"))
+        .filter_map(|path| match load(&path) {
+            Ok(theme) => Some(theme),
+            Err(error) => {
+                eprintln!("could not load the theme {}: {error}", path.display());
+                None
+            }
+        })
+        .collect();
+    themes.sort_by(|a, b| a.0.cmp(&b.0));
+    themes
+}
+
+// Reads a theme file: a `name` and `"#rrggbb"` colours named after the
+// `Palette` fields. Only a restricted subset of TOML is understood: one
+// `key = "value"` pair per line, with double-quoted values and comments on
+// lines of their own. Colours left out are those of the dark theme, and the
+// name defaults to the file name.
+//
+//     name = "Solarized"
+//     background = "#002b36"
+//     player_x = "#dc322f"
+pub fn load(path: &Path) -> io::Result<(String, Palette)> {
+    let text = fs::read_to_string(path)?;
+    let (name, palette) = parse(&text).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    let name = name
+        .or_else(|| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+        .unwrap_or_default();
+    Ok((name, palette))
+}
+
+fn parse(text: &str) -> Result<(Option<String>, Palette), ParsePaletteError> {
+    let mut name = None;
+    let mut palette = Palette::DARK;
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let error = || ParsePaletteError(line.to_string());
+        let (key, value) = line.split_once('=').ok_or_else(error)?;
+        let value = value
+            .trim()
+            .strip_prefix('"')
+            .and_then(|rest| rest.strip_suffix('"'))
+            .ok_or_else(error)?;
+
+        match key.trim() {
+            "name" => name = Some(value.to_string()),
+            key => *palette.color_mut(key).ok_or_else(error)? = parse_color(value).ok_or_else(error)?,
+        }
+    }
+    Ok((name, palette))
+}
+
+fn parse_color(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.chars().all(|digit| digit.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok().map(rgb)
+}
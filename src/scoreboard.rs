@@ -0,0 +1,134 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::ai::Level;
+use crate::board::Player;
+use crate::game::{Controller, Game, Status};
+
+/// Results of finished games from one side's point of view: X in two player
+/// games, the human in games against the AI.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Tally {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl Tally {
+    pub fn total(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+
+    fn add(&mut self, winner: Option<Player>, side: Player) {
+        match winner {
+            Some(winner) if winner == side => self.wins += 1,
+            Some(_) => self.losses += 1,
+            None => self.draws += 1,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Scoreboard {
+    pub two_players: Tally,
+    /// One tally per level, in the order of `Level::ALL`.
+    pub against_ai: [Tally; Level::ALL.len()],
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseScoreboardError(String);
+
+impl fmt::Display for ParseScoreboardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid scoreboard line: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseScoreboardError {}
+
+impl Scoreboard {
+    pub fn against(&self, level: Level) -> Tally {
+        self.against_ai[level.index()]
+    }
+
+    /// Adds the result of a finished game. Only games between two humans or
+    /// between a human and the AI are counted; returns whether it was.
+    pub fn record(&mut self, game: &Game) -> bool {
+        let winner = match game.status() {
+            Status::Playing => return false,
+            Status::Winner => game.winner(),
+            Status::Draw => None,
+        };
+
+        match [game.controller(Player::X), game.controller(Player::O)] {
+            [Controller::Human, Controller::Human] => self.two_players.add(winner, Player::X),
+            [Controller::Human, Controller::Ai(level)] => self.against_ai[level.index()].add(winner, Player::X),
+            [Controller::Ai(level), Controller::Human] => self.against_ai[level.index()].add(winner, Player::O),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Reads a scoreboard saved with `save`. A missing file is an empty
+    /// scoreboard.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => text.parse().map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Scoreboard::default()),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_string())
+    }
+}
+
+/// One line per tally: its name, then the wins, losses and draws.
+impl fmt::Display for Scoreboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tallies = std::iter::once(("TwoPlayers".to_string(), self.two_players))
+            .chain(Level::ALL.iter().map(|level| (format!("{level:?}"), self.against(*level))));
+
+        for (name, tally) in tallies {
+            writeln!(f, "{name} {} {} {}", tally.wins, tally.losses, tally.draws)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Scoreboard {
+    type Err = ParseScoreboardError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut scoreboard = Scoreboard::default();
+
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let error = || ParseScoreboardError(line.to_string());
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [name, wins, losses, draws] = fields[..] else {
+                return Err(error());
+            };
+
+            let tally = Tally {
+                wins: wins.parse().map_err(|_| error())?,
+                losses: losses.parse().map_err(|_| error())?,
+                draws: draws.parse().map_err(|_| error())?,
+            };
+
+            if name == "TwoPlayers" {
+                scoreboard.two_players = tally;
+            } else {
+                let level = Level::from_name(name).ok_or_else(error)?;
+                scoreboard.against_ai[level.index()] = tally;
+            }
+        }
+        Ok(scoreboard)
+    }
+}
@@ -0,0 +1,487 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::board::{Board, CellState, Player};
+use crate::transposition::{Bound, Entry, TranspositionTable};
+
+/// Score of a won position, minus the number of plies needed to get there so
+/// that faster wins (and slower losses) are preferred.
+pub const WIN_SCORE: i32 = 1_000_000;
+
+pub const DEFAULT_NODE_BUDGET: u64 = 200_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub depth: Option<usize>,
+    pub nodes: Option<u64>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            depth: None,
+            nodes: Some(DEFAULT_NODE_BUDGET),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchResult {
+    pub index: usize,
+    pub score: i32,
+    pub depth: usize,
+    pub nodes: u64,
+}
+
+impl SearchResult {
+    pub fn is_decisive(&self) -> bool {
+        is_decisive(self.score)
+    }
+}
+
+fn is_decisive(score: i32) -> bool {
+    score.abs() > WIN_SCORE / 2
+}
+
+fn plies_to_end(score: i32) -> usize {
+    (WIN_SCORE - score.abs()) as usize
+}
+
+/// Win scores are stored relative to the node they were found at, so that
+/// they stay correct when the position is reached at another ply.
+fn to_table(score: i32, ply: usize) -> i32 {
+    match score {
+        score if score > WIN_SCORE / 2 => score + ply as i32,
+        score if score < -WIN_SCORE / 2 => score - ply as i32,
+        score => score,
+    }
+}
+
+fn from_table(score: i32, ply: usize) -> i32 {
+    match score {
+        score if score > WIN_SCORE / 2 => score - ply as i32,
+        score if score < -WIN_SCORE / 2 => score + ply as i32,
+        score => score,
+    }
+}
+
+/// A position the search can explore, by playing and taking back marks on
+/// its board. Wins, draws and the transposition table keys all come from
+/// the board; the moves worth trying and the scoring of the positions where
+/// the search stops are up to the implementation.
+pub trait Searchable {
+    fn board(&self) -> &Board;
+
+    /// Puts a mark of `player` on the free cell `spot`.
+    fn play(&mut self, spot: usize, player: Player);
+
+    /// Takes back the mark of `player` on `spot`, the last one played.
+    fn undo(&mut self, spot: usize, player: Player);
+
+    /// Moves to search for `player`, the most promising first. Cells left
+    /// out are never searched.
+    fn moves(&mut self, player: Player) -> Vec<usize>;
+
+    /// Static evaluation from the point of view of `player`, who is to move.
+    fn evaluate(&mut self, player: Player) -> i32;
+}
+
+/// Every free cell is searched, ordered by how many open lines it extends
+/// for either side, and positions are scored by `evaluate`.
+impl Searchable for Board {
+    fn board(&self) -> &Board {
+        self
+    }
+
+    fn play(&mut self, spot: usize, player: Player) {
+        self.make_move(spot, player);
+    }
+
+    fn undo(&mut self, spot: usize, _player: Player) {
+        self.clear(spot);
+    }
+
+    fn moves(&mut self, player: Player) -> Vec<usize> {
+        let mut moves: Vec<(i32, usize)> = self
+            .available_moves()
+            .into_iter()
+            .map(|spot| (move_potential(self, spot, player), spot))
+            .collect();
+
+        moves.sort_by_key(|&(potential, _)| std::cmp::Reverse(potential));
+        moves.into_iter().map(|(_, spot)| spot).collect()
+    }
+
+    fn evaluate(&mut self, player: Player) -> i32 {
+        evaluate(self, player)
+    }
+}
+
+/// Iterative deepening negamax with alpha-beta pruning. Each iteration is
+/// searched one ply deeper than the last, with the previous best move tried
+/// first, until the depth or node budget runs out, the outcome is forced or
+/// there is a single move to try. An iteration cut short by the node budget
+/// is thrown away.
+pub fn search<P: Searchable + Clone>(position: &P, player: Player, limits: Limits) -> Option<SearchResult> {
+    search_with_table(position, player, limits, &mut TranspositionTable::new(), &AtomicBool::new(false))
+}
+
+/// Same as `search`, reusing and filling `table` so that later searches in
+/// the same game start from what was learned here. Raising `stop` from
+/// another thread ends the search like an exhausted node budget would.
+pub fn search_with_table<P: Searchable + Clone>(
+    position: &P,
+    player: Player,
+    limits: Limits,
+    table: &mut TranspositionTable,
+    stop: &AtomicBool,
+) -> Option<SearchResult> {
+    let board = position.board();
+    let free = board.available_moves().len();
+    if free == 0 {
+        return None;
+    }
+
+    table.prepare(board);
+    let symmetries = board.symmetries();
+    let inverses = symmetries
+        .iter()
+        .map(|symmetry| {
+            let mut inverse = vec![0; symmetry.len()];
+            for (position, &image) in symmetry.iter().enumerate() {
+                inverse[image] = position;
+            }
+            inverse
+        })
+        .collect();
+
+    let mut searcher = Searcher {
+        position: position.clone(),
+        free,
+        nodes: 0,
+        limits,
+        stop,
+        aborted: false,
+        hashes: table.hashes(board),
+        inverses,
+        table,
+    };
+
+    let forced = searcher.position.moves(player).len() == 1;
+    let max_depth = limits.depth.unwrap_or(free).clamp(1, free);
+    let mut best: Option<SearchResult> = None;
+
+    for depth in 1..=max_depth {
+        let Some((index, score)) = searcher.root(player, depth, best.map(|result| result.index)) else {
+            break;
+        };
+
+        best = Some(SearchResult {
+            index,
+            score,
+            depth,
+            nodes: searcher.nodes,
+        });
+
+        // A forced result is final once it lies within the searched depth:
+        // any quicker one would have been seen by this iteration already.
+        if forced || is_decisive(score) && plies_to_end(score) <= depth {
+            break;
+        }
+    }
+
+    best
+}
+
+struct Searcher<'a, P> {
+    position: P,
+    free: usize,
+    nodes: u64,
+    limits: Limits,
+    stop: &'a AtomicBool,
+    aborted: bool,
+    /// Incremental hash of the board under each symmetry.
+    hashes: Vec<u64>,
+    inverses: Vec<Vec<usize>>,
+    table: &'a mut TranspositionTable,
+}
+
+impl<P: Searchable> Searcher<'_, P> {
+    /// Canonical key of the current position with `player` to move, and the
+    /// symmetry that brings it into canonical orientation.
+    fn canonical(&self, player: Player) -> (u64, usize) {
+        let (symmetry, hash) = self
+            .hashes
+            .iter()
+            .copied()
+            .enumerate()
+            .min_by_key(|&(_, hash)| hash)
+            .expect("a board has at least the identity symmetry");
+        (hash ^ self.table.to_move_key(player), symmetry)
+    }
+
+    fn table_move(&self, key: u64, symmetry: usize) -> Option<usize> {
+        self.table
+            .probe(key)
+            .and_then(|entry| entry.best)
+            .map(|best| self.inverses[symmetry][best])
+    }
+
+    fn store(&mut self, key: u64, symmetry: usize, depth: usize, score: i32, bound: Bound, best: Option<usize>) {
+        let best = best.map(|spot| self.position.board().symmetries()[symmetry][spot]);
+        self.table.store(Entry {
+            key,
+            depth,
+            score,
+            bound,
+            best,
+        });
+    }
+
+    fn root(&mut self, player: Player, depth: usize, first: Option<usize>) -> Option<(usize, i32)> {
+        let (key, symmetry) = self.canonical(player);
+        let first = first.or_else(|| self.table_move(key, symmetry));
+        let moves = self.ordered_moves(player, first);
+
+        let mut alpha = -WIN_SCORE;
+        let mut best: Option<(usize, i32)> = None;
+
+        for spot in moves {
+            let score = self.score_move(spot, player, depth, 0, alpha, WIN_SCORE);
+            if self.aborted {
+                return None;
+            }
+
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((spot, score));
+            }
+            alpha = alpha.max(score);
+        }
+
+        if let Some((spot, score)) = best {
+            self.store(key, symmetry, depth, score, Bound::Exact, Some(spot));
+        }
+        best
+    }
+
+    fn negamax(&mut self, player: Player, depth: usize, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        let (key, symmetry) = self.canonical(player);
+        let entry = self.table.probe(key);
+
+        if let Some(entry) = entry.filter(|entry| entry.depth >= depth) {
+            let score = from_table(entry.score, ply);
+            match entry.bound {
+                Bound::Exact => return score,
+                Bound::Lower if score >= beta => return score,
+                Bound::Upper if score <= alpha => return score,
+                _ => {}
+            }
+        }
+
+        let first = entry
+            .and_then(|entry| entry.best)
+            .map(|best| self.inverses[symmetry][best]);
+        let original_alpha = alpha;
+        let mut best = -WIN_SCORE;
+        let mut best_spot = None;
+
+        for spot in self.ordered_moves(player, first) {
+            let score = self.score_move(spot, player, depth, ply, alpha, beta);
+            if self.aborted {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+                best_spot = Some(spot);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best <= original_alpha {
+            Bound::Upper
+        } else if best >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.store(key, symmetry, depth, to_table(best, ply), bound, best_spot);
+        best
+    }
+
+    fn play(&mut self, spot: usize, player: Player) {
+        self.position.play(spot, player);
+        self.free -= 1;
+        self.toggle_hashes(spot, player);
+    }
+
+    fn undo(&mut self, spot: usize, player: Player) {
+        self.position.undo(spot, player);
+        self.free += 1;
+        self.toggle_hashes(spot, player);
+    }
+
+    fn toggle_hashes(&mut self, spot: usize, player: Player) {
+        for (hash, symmetry) in self.hashes.iter_mut().zip(self.position.board().symmetries()) {
+            *hash ^= self.table.cell_key(symmetry[spot], player);
+        }
+    }
+
+    /// Plays `spot` for `player` and scores the resulting position from
+    /// their point of view.
+    fn score_move(&mut self, spot: usize, player: Player, depth: usize, ply: usize, alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.limits.nodes.is_some_and(|budget| self.nodes > budget) || self.stop.load(Ordering::Relaxed) {
+            self.aborted = true;
+            return 0;
+        }
+
+        self.play(spot, player);
+
+        let score = if self.position.board().is_winning_move(spot, player) {
+            WIN_SCORE - (ply as i32 + 1)
+        } else if self.free == 0 {
+            0
+        } else if depth == 1 {
+            -self.position.evaluate(player.opponent())
+        } else {
+            -self.negamax(player.opponent(), depth - 1, ply + 1, -beta, -alpha)
+        };
+
+        self.undo(spot, player);
+        score
+    }
+
+    /// Moves of the position, strongest first so that alpha-beta prunes the
+    /// most. `first`, the best move from a previous search, goes before all
+    /// of them when it is among them.
+    fn ordered_moves(&mut self, player: Player, first: Option<usize>) -> Vec<usize> {
+        let mut moves = self.position.moves(player);
+        if let Some(at) = first.and_then(|first| moves.iter().position(|&spot| spot == first)) {
+            moves[..=at].rotate_right(1);
+        }
+        moves
+    }
+}
+
+fn line_weight(count: usize) -> i32 {
+    1 << (2 * count)
+}
+
+fn move_potential(board: &Board, spot: usize, player: Player) -> i32 {
+    board
+        .lines_through(spot)
+        .map(|line| {
+            let (mine, theirs) = line_counts(board, line, player);
+            match (mine, theirs) {
+                (0, 0) => 1,
+                (mine, 0) => line_weight(mine) * 2,
+                (0, theirs) => line_weight(theirs),
+                _ => 0,
+            }
+        })
+        .sum()
+}
+
+fn line_counts(board: &Board, line: &[usize], player: Player) -> (usize, usize) {
+    line.iter().fold((0, 0), |(mine, theirs), &pos| match board.cells()[pos] {
+        CellState::Occupied(owner) if owner == player => (mine + 1, theirs),
+        CellState::Occupied(_) => (mine, theirs + 1),
+        CellState::Empty => (mine, theirs),
+    })
+}
+
+/// Static evaluation from the point of view of `player`: every line still
+/// open for only one side counts for that side, more so the fuller it is.
+pub fn evaluate(board: &Board, player: Player) -> i32 {
+    board
+        .lines()
+        .iter()
+        .map(|line| match line_counts(board, line, player) {
+            (mine, 0) if mine > 0 => line_weight(mine),
+            (0, theirs) if theirs > 0 => -line_weight(theirs),
+            _ => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board_with(cells: &[(&str, Player)]) -> Board {
+        let mut board = Board::default();
+        for &(name, player) in cells {
+            board.make_move(board.parse_cell(name).unwrap(), player);
+        }
+        board
+    }
+
+    #[test]
+    fn takes_the_quickest_win() {
+        // a3 completes the a column at once.
+        let board = board_with(&[("a1", Player::X), ("a2", Player::X), ("b2", Player::O), ("c3", Player::O)]);
+        let result = search(&board, Player::X, Limits::default()).unwrap();
+        assert_eq!(result.index, board.parse_cell("a3").unwrap());
+        assert_eq!(result.score, WIN_SCORE - 1);
+        assert_eq!(result.depth, 1);
+    }
+
+    /// Whether the search, playing `ai`, loses any game from `board` with
+    /// `player` to move, whatever the opponent plays.
+    fn can_lose(board: &mut Board, player: Player, ai: Player) -> bool {
+        let moves = if player == ai {
+            vec![search(board, player, Limits::default()).unwrap().index]
+        } else {
+            board.available_moves()
+        };
+
+        moves.into_iter().any(|spot| {
+            board.make_move(spot, player);
+            let lost = if board.is_winning_move(spot, player) {
+                player != ai
+            } else {
+                !board.available_moves().is_empty() && can_lose(board, player.opponent(), ai)
+            };
+            board.clear(spot);
+            lost
+        })
+    }
+
+    #[test]
+    fn reused_tables_give_the_same_scores() {
+        let board = board_with(&[("b2", Player::X), ("a1", Player::O), ("c1", Player::X)]);
+        let mut table = TranspositionTable::new();
+        let stop = AtomicBool::new(false);
+        let fresh = search(&board, Player::O, Limits::default()).unwrap();
+
+        for _ in 0..2 {
+            let reused = search_with_table(&board, Player::O, Limits::default(), &mut table, &stop).unwrap();
+            assert_eq!(reused.score, fresh.score);
+        }
+
+        // Every rotation and reflection is found in the table under the
+        // same key, and has to keep its score.
+        for symmetry in board.symmetries() {
+            let mut turned = board.cleared();
+            for player in Player::ALL {
+                for position in board.played_moves(player) {
+                    turned.make_move(symmetry[position], player);
+                }
+            }
+            let fresh = search(&turned, Player::O, Limits::default()).unwrap();
+            let reused = search_with_table(&turned, Player::O, Limits::default(), &mut table, &stop).unwrap();
+            assert_eq!(reused.score, fresh.score);
+            assert!(turned.is_free(reused.index));
+        }
+    }
+
+    #[test]
+    fn never_loses_on_three_by_three() {
+        for ai in Player::ALL {
+            assert!(!can_lose(&mut Board::default(), Player::X, ai), "{ai} lost");
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::fmt;
+
+use crate::board::{Board, CellState, Player};
+
+pub const DEFAULT_CAPACITY: usize = 1 << 18;
+
+const PLAYERS: usize = Player::ALL.len();
+
+/// Deterministic splitmix64 stream, so that keys are identical across runs.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub key: u64,
+    pub depth: usize,
+    pub score: i32,
+    pub bound: Bound,
+    /// Best move found, in the canonical orientation of the position.
+    pub best: Option<usize>,
+}
+
+/// Zobrist keys for one board size, with one random key per cell and mark
+/// and one per side to move.
+#[derive(Clone)]
+struct Zobrist {
+    cells: Vec<[u64; PLAYERS]>,
+    to_move: [u64; PLAYERS],
+}
+
+impl Zobrist {
+    fn new(cell_count: usize) -> Self {
+        let mut state = 0x5eed_u64;
+        Zobrist {
+            cells: (0..cell_count)
+                .map(|_| std::array::from_fn(|_| splitmix64(&mut state)))
+                .collect(),
+            to_move: std::array::from_fn(|_| splitmix64(&mut state)),
+        }
+    }
+}
+
+/// Transposition table keyed on the canonical form of a position: the
+/// smallest of its Zobrist hashes over all the board symmetries, so that a
+/// position and its rotations and reflections share a single entry.
+#[derive(Clone)]
+pub struct TranspositionTable {
+    zobrist: Zobrist,
+    entries: Vec<Option<Entry>>,
+    capacity: usize,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl fmt::Debug for TranspositionTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TranspositionTable")
+            .field("capacity", &self.capacity)
+            .field("used", &self.len())
+            .finish()
+    }
+}
+
+impl TranspositionTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Entries are only allocated on the first store.
+    pub fn with_capacity(capacity: usize) -> Self {
+        TranspositionTable {
+            zobrist: Zobrist::new(0),
+            entries: Vec::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.iter().filter(|entry| entry.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.iter().all(Option::is_none)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Makes the keys match the size of `board`, dropping every entry if
+    /// they had been generated for another board.
+    pub fn prepare(&mut self, board: &Board) {
+        if self.zobrist.cells.len() != board.cell_count() {
+            self.zobrist = Zobrist::new(board.cell_count());
+            self.clear();
+        }
+    }
+
+    pub fn cell_key(&self, position: usize, player: Player) -> u64 {
+        self.zobrist.cells[position][player.index()]
+    }
+
+    pub fn to_move_key(&self, player: Player) -> u64 {
+        self.zobrist.to_move[player.index()]
+    }
+
+    /// Hash of the board seen through each of its symmetries, in the order
+    /// of `Board::symmetries`.
+    pub fn hashes(&self, board: &Board) -> Vec<u64> {
+        board
+            .symmetries()
+            .iter()
+            .map(|symmetry| {
+                board.cells().iter().enumerate().fold(0, |hash, (position, cell)| match cell {
+                    CellState::Occupied(player) => hash ^ self.cell_key(symmetry[position], *player),
+                    CellState::Empty => hash,
+                })
+            })
+            .collect()
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        self.entries
+            .get((key % self.capacity as u64) as usize)
+            .copied()
+            .flatten()
+            .filter(|entry| entry.key == key)
+    }
+
+    /// A new position always takes over its slot, while an entry for the
+    /// same position is only replaced by an equally deep or deeper search.
+    pub fn store(&mut self, entry: Entry) {
+        if self.entries.is_empty() {
+            self.entries = vec![None; self.capacity];
+        }
+
+        let slot = &mut self.entries[(entry.key % self.capacity as u64) as usize];
+        if slot.is_none_or(|existing| existing.key != entry.key || existing.depth <= entry.depth) {
+            *slot = Some(entry);
+        }
+    }
+}
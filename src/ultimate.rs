@@ -0,0 +1,224 @@
+//! Ultimate tic-tac-toe: a 9x9 board split into nine 3x3 local boards. The
+//! cell a player picks inside a local board sends the opponent to the local
+//! board in the same place, unless that one is already decided, in which case
+//! any open local board will do. Three in a row on a local board claims it on
+//! the meta-board, and three claimed local boards in a row win the game.
+//!
+//! The cells are an ordinary 9x9 `Board`; `MetaBoard` keeps the rest of the
+//! state alongside it.
+
+use crate::board::{Board, CellState, Player};
+use crate::mcts::Position;
+
+/// Cells across a local board, and local boards across the meta-board.
+const SIDE: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MetaBoard {
+    /// Local boards by position, marked with the player who claimed them.
+    claimed: Board,
+    /// Local board the next move has to go in, `None` when any open one will
+    /// do.
+    forced: Option<usize>,
+}
+
+impl Default for MetaBoard {
+    fn default() -> Self {
+        MetaBoard::new()
+    }
+}
+
+impl MetaBoard {
+    pub fn new() -> Self {
+        MetaBoard {
+            claimed: Board::new(SIDE, SIDE, SIDE),
+            forced: None,
+        }
+    }
+
+    /// Local board holding cell `index` of the 9x9 board.
+    pub fn local_board(index: usize) -> usize {
+        let (column, row) = (index % (SIDE * SIDE), index / (SIDE * SIDE));
+        row / SIDE * SIDE + column / SIDE
+    }
+
+    /// Position of cell `index` within its local board.
+    pub fn local_cell(index: usize) -> usize {
+        let (column, row) = (index % (SIDE * SIDE), index / (SIDE * SIDE));
+        row % SIDE * SIDE + column % SIDE
+    }
+
+    /// Cells of local board `local`, in the order of their local positions.
+    pub fn local_cells(local: usize) -> [usize; SIDE * SIDE] {
+        let (left, top) = (local % SIDE * SIDE, local / SIDE * SIDE);
+        std::array::from_fn(|cell| (top + cell / SIDE) * SIDE * SIDE + left + cell % SIDE)
+    }
+
+    pub fn claimed(&self) -> &Board {
+        &self.claimed
+    }
+
+    pub fn forced(&self) -> Option<usize> {
+        self.forced
+    }
+
+    /// Whether local board `local` is claimed or has no free cell left.
+    pub fn is_decided(&self, board: &Board, local: usize) -> bool {
+        !self.claimed.is_free(local) || Self::local_cells(local).iter().all(|&cell| !board.is_free(cell))
+    }
+
+    /// Local boards the next move may go in.
+    pub fn active_boards(&self, board: &Board) -> Vec<usize> {
+        match self.forced {
+            Some(local) => vec![local],
+            None => (0..SIDE * SIDE).filter(|&local| !self.is_decided(board, local)).collect(),
+        }
+    }
+
+    pub fn is_legal(&self, board: &Board, index: usize) -> bool {
+        board.is_free(index)
+            && self.winner().is_none()
+            && self.active_boards(board).contains(&Self::local_board(index))
+    }
+
+    pub fn available_moves(&self, board: &Board) -> Vec<usize> {
+        if self.winner().is_some() {
+            return Vec::new();
+        }
+        self.active_boards(board)
+            .into_iter()
+            .flat_map(Self::local_cells)
+            .filter(|&cell| board.is_free(cell))
+            .collect()
+    }
+
+    /// Updates the meta-board after `player` played on `index` of `board`.
+    /// Returns whether the move won the game.
+    pub fn record(&mut self, board: &Board, index: usize, player: Player) -> bool {
+        let local = Self::local_board(index);
+        let cells = Self::local_cells(local);
+        // The lines of a local board are those of the meta-board, which has
+        // the same shape.
+        let claims = self.claimed.is_free(local)
+            && self
+                .claimed
+                .lines_through(Self::local_cell(index))
+                .any(|line| line.iter().all(|&cell| board.cell(cells[cell]) == Some(CellState::Occupied(player))));
+
+        let won = claims && {
+            self.claimed.make_move(local, player);
+            self.claimed.is_winning_move(local, player)
+        };
+
+        let next = Self::local_cell(index);
+        self.forced = (!self.is_decided(board, next)).then_some(next);
+        won
+    }
+
+    pub fn winner(&self) -> Option<Player> {
+        Player::ALL.into_iter().find(|&player| self.claimed.check_win(player))
+    }
+
+    /// Lines of local boards claimed by `player`.
+    pub fn winning_lines(&self, player: Player) -> impl Iterator<Item = &[usize]> + '_ {
+        self.claimed.winning_lines(player)
+    }
+}
+
+/// The cells and the meta-board together, as searched by the AI.
+#[derive(Debug, Clone)]
+pub struct UltimatePosition {
+    pub board: Board,
+    pub meta: MetaBoard,
+}
+
+impl Position for UltimatePosition {
+    fn available_moves(&self) -> Vec<usize> {
+        self.meta.available_moves(&self.board)
+    }
+
+    fn play(&mut self, index: usize, player: Player) -> bool {
+        self.board.make_move(index, player);
+        self.meta.record(&self.board, index, player)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Marks of a full local board without three in a row.
+    const DRAWN: [Player; SIDE * SIDE] = {
+        use Player::{O, X};
+        [X, O, X, X, O, O, O, X, X]
+    };
+
+    fn play(board: &mut Board, meta: &mut MetaBoard, index: usize, player: Player) -> bool {
+        board.make_move(index, player);
+        meta.record(board, index, player)
+    }
+
+    fn fill_drawn(board: &mut Board, meta: &mut MetaBoard, local: usize) {
+        for (cell, player) in MetaBoard::local_cells(local).into_iter().zip(DRAWN) {
+            assert!(!play(board, meta, cell, player));
+        }
+    }
+
+    #[test]
+    fn moves_send_the_opponent_to_the_matching_board() {
+        let mut board = Board::new(9, 9, 3);
+        let mut meta = MetaBoard::new();
+        let middle_of_top_left = MetaBoard::local_cells(0)[4];
+        play(&mut board, &mut meta, middle_of_top_left, Player::X);
+
+        assert_eq!(meta.forced(), Some(4));
+        assert!(meta.is_legal(&board, MetaBoard::local_cells(4)[0]));
+        assert!(!meta.is_legal(&board, MetaBoard::local_cells(0)[0]));
+        assert_eq!(meta.available_moves(&board), MetaBoard::local_cells(4));
+    }
+
+    #[test]
+    fn any_open_board_once_the_target_is_claimed() {
+        let mut board = Board::new(9, 9, 3);
+        let mut meta = MetaBoard::new();
+        let middle = MetaBoard::local_cells(4);
+        for cell in [0, 1, 2] {
+            play(&mut board, &mut meta, middle[cell], Player::X);
+        }
+        assert_eq!(meta.claimed().cell(4), Some(CellState::Occupied(Player::X)));
+
+        // The middle cell of any board sends the next player to the middle board.
+        play(&mut board, &mut meta, MetaBoard::local_cells(7)[4], Player::O);
+        assert_eq!(meta.forced(), None);
+        assert!(meta.is_legal(&board, MetaBoard::local_cells(0)[0]));
+        assert!(meta.is_legal(&board, MetaBoard::local_cells(8)[8]));
+        assert!(!meta.is_legal(&board, middle[8]));
+        assert_eq!(meta.active_boards(&board), [0, 1, 2, 3, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn full_boards_count_as_decided() {
+        let mut board = Board::new(9, 9, 3);
+        let mut meta = MetaBoard::new();
+        fill_drawn(&mut board, &mut meta, 4);
+        assert!(meta.claimed().is_free(4));
+        assert!(meta.is_decided(&board, 4));
+
+        play(&mut board, &mut meta, MetaBoard::local_cells(0)[4], Player::X);
+        assert_eq!(meta.forced(), None);
+        assert!(!meta.active_boards(&board).contains(&4));
+    }
+
+    #[test]
+    fn drawn_once_every_board_is_decided() {
+        let mut board = Board::new(9, 9, 3);
+        let mut meta = MetaBoard::new();
+        for local in 0..SIDE * SIDE {
+            fill_drawn(&mut board, &mut meta, local);
+        }
+
+        assert_eq!(meta.winner(), None);
+        assert!(meta.active_boards(&board).is_empty());
+        assert!(meta.available_moves(&board).is_empty());
+    }
+}
@@ -0,0 +1,69 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Size, position and mode of the window, remembered between sessions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowState {
+    pub size: (u32, u32),
+    pub position: Option<(i32, i32)>,
+    pub fullscreen: bool,
+}
+
+impl Default for WindowState {
+    fn default() -> Self {
+        WindowState {
+            size: (1200, 1200),
+            position: None,
+            fullscreen: false,
+        }
+    }
+}
+
+impl WindowState {
+    pub fn path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("tic-tac-toe-iced").join("window.txt"))
+    }
+
+    // Falls back to the defaults when nothing was saved yet or the file
+    // cannot be read.
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|text| Self::parse(&text))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_text())
+    }
+
+    // "size 800 600", "position 10 20" and "fullscreen false" lines, the
+    // position only once known.
+    fn to_text(self) -> String {
+        let mut text = format!("size {} {}\n", self.size.0, self.size.1);
+        if let Some((x, y)) = self.position {
+            text.push_str(&format!("position {x} {y}\n"));
+        }
+        text.push_str(&format!("fullscreen {}\n", self.fullscreen));
+        text
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let mut state = WindowState::default();
+
+        for line in text.lines() {
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["size", width, height] => state.size = (width.parse().ok()?, height.parse().ok()?),
+                ["position", x, y] => state.position = Some((x.parse().ok()?, y.parse().ok()?)),
+                ["fullscreen", fullscreen] => state.fullscreen = fullscreen.parse().ok()?,
+                [] => {}
+                _ => return None,
+            }
+        }
+        (state.size.0 > 0 && state.size.1 > 0).then_some(state)
+    }
+}